@@ -1,6 +0,0 @@
-cd app
-echo "Building wasm target..."
-wasm-pack build --target web
-cd ..
-echo "Building server..."
-cargo run
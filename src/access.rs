@@ -0,0 +1,183 @@
+use crate::server::Settings;
+use actix_web::{
+	body::{BoxBody, MessageBody},
+	cookie::{Cookie, SameSite},
+	dev::{ServiceRequest, ServiceResponse},
+	http::header::{self, HeaderValue},
+	middleware::Next,
+	web, HttpRequest, HttpResponse,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use std::net::IpAddr;
+
+const COOKIE: &str = "cui_token";
+const TOKEN_PARAM: &str = "token";
+
+/// A random token for `--token` without a value.
+pub fn generate_token() -> String {
+	std::iter::repeat_with(fastrand::alphanumeric).take(16).collect()
+}
+
+/// Reads a `USER:PASSWORD` pair.
+pub fn parse_basic_auth(arg: &str) -> Result<String, String> {
+	match arg.split_once(':') {
+		Some((user, _)) if !user.is_empty() => Ok(arg.to_string()),
+		_ => Err(format!("expected `USER:PASSWORD`, found `{}`", arg)),
+	}
+}
+
+/// The addresses other devices can reach the server at, when it listens on all of them.
+pub fn lan_addresses(settings: &Settings) -> Vec<IpAddr> {
+	let unspecified = settings
+		.host
+		.parse::<IpAddr>()
+		.is_ok_and(|address| address.is_unspecified());
+	if !unspecified {
+		return Vec::new();
+	}
+	let mut addresses: Vec<_> = if_addrs::get_if_addrs()
+		.unwrap_or_default()
+		.into_iter()
+		.filter(|interface| !interface.is_loopback())
+		.map(|interface| interface.ip())
+		.collect();
+	// IPv4 first, it's what people type into phones
+	addresses.sort_by_key(|address| (address.is_ipv6(), *address));
+	addresses.dedup();
+	addresses
+}
+
+/// Asks requests from other machines for the token or password in the settings.
+/// Requests from this machine are always let through.
+pub async fn middleware(
+	req: ServiceRequest,
+	next: Next<impl MessageBody + 'static>,
+) -> actix_web::Result<ServiceResponse<BoxBody>> {
+	let settings = match req.app_data::<web::Data<Settings>>() {
+		Some(settings) if settings.token.is_some() || settings.basic_auth.is_some() => settings.clone(),
+		_ => return next.call(req).await.map(ServiceResponse::map_into_boxed_body),
+	};
+	let local = req.peer_addr().is_none_or(|peer| peer.ip().is_loopback());
+	if local {
+		return next.call(req).await.map(ServiceResponse::map_into_boxed_body);
+	}
+
+	if let Some(token) = &settings.token {
+		if req.cookie(COOKIE).is_some_and(|cookie| cookie.value() == token) || bearer(&req) == Some(token) {
+			return next.call(req).await.map(ServiceResponse::map_into_boxed_body);
+		}
+		// a link with the token sets the cookie, then drops the token from the address bar
+		if query_token(&req).as_deref() == Some(token.as_str()) {
+			let cookie = Cookie::build(COOKIE, token.clone())
+				.path("/")
+				.http_only(true)
+				.same_site(SameSite::Strict)
+				.finish();
+			let res = HttpResponse::Found()
+				.cookie(cookie)
+				.insert_header((header::LOCATION, without_token(&req)))
+				.finish();
+			return Ok(req.into_response(res));
+		}
+	}
+
+	if let Some(credentials) = &settings.basic_auth {
+		if basic(&req).as_deref() == Some(credentials.as_str()) {
+			return next.call(req).await.map(ServiceResponse::map_into_boxed_body);
+		}
+		let res = HttpResponse::Unauthorized()
+			.insert_header((
+				header::WWW_AUTHENTICATE,
+				HeaderValue::from_static(r#"Basic realm="cui-tools", charset="UTF-8""#),
+			))
+			.body("this server needs a user name and password");
+		return Ok(req.into_response(res));
+	}
+
+	log::debug!("refused {} from {:?} without a token", req.path(), req.peer_addr());
+	let res = HttpResponse::Unauthorized().body("open the link cui-tools printed, it carries the access token");
+	Ok(req.into_response(res))
+}
+
+fn bearer(req: &ServiceRequest) -> Option<&str> {
+	req.headers()
+		.get(header::AUTHORIZATION)?
+		.to_str()
+		.ok()?
+		.strip_prefix("Bearer ")
+}
+
+fn basic(req: &ServiceRequest) -> Option<String> {
+	let encoded = req
+		.headers()
+		.get(header::AUTHORIZATION)?
+		.to_str()
+		.ok()?
+		.strip_prefix("Basic ")?;
+	String::from_utf8(STANDARD.decode(encoded.trim()).ok()?).ok()
+}
+
+fn query_token(req: &ServiceRequest) -> Option<String> {
+	web::Query::<Vec<(String, String)>>::from_query(req.query_string())
+		.ok()?
+		.0
+		.into_iter()
+		.find(|(name, _)| name == TOKEN_PARAM)
+		.map(|(_, value)| value)
+}
+
+fn without_token(req: &ServiceRequest) -> String {
+	let query: Vec<_> = req
+		.query_string()
+		.split('&')
+		.filter(|pair| !pair.is_empty() && pair.split('=').next() != Some(TOKEN_PARAM))
+		.collect();
+	match query.is_empty() {
+		true => req.path().to_string(),
+		false => format!("{}?{}", req.path(), query.join("&")),
+	}
+}
+
+/// Whether the request came from a page of this server, so other sites can't talk to the tools.
+/// Requests without the headers browsers add come from other programs and are let through.
+pub fn same_origin(req: &HttpRequest) -> bool {
+	let headers = req.headers();
+	if let Some(site) = headers.get("sec-fetch-site") {
+		if site != "same-origin" {
+			return false;
+		}
+	}
+	match headers.get(header::ORIGIN) {
+		Some(origin) => {
+			let origin = origin.to_str().unwrap_or_default();
+			let host = origin.split_once("://").map(|(_, host)| host);
+			host.is_some() && host == headers.get(header::HOST).and_then(|host| host.to_str().ok())
+		}
+		None => true,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use actix_web::test::TestRequest;
+
+	fn request(headers: &[(&'static str, &'static str)]) -> HttpRequest {
+		headers
+			.iter()
+			.fold(TestRequest::post().insert_header(("host", "localhost:8080")), |req, &header| {
+				req.insert_header(header)
+			})
+			.to_http_request()
+	}
+
+	#[test]
+	fn only_this_server_pages_are_same_origin() {
+		assert!(same_origin(&request(&[])));
+		assert!(same_origin(&request(&[("origin", "http://localhost:8080"), ("sec-fetch-site", "same-origin")])));
+		assert!(!same_origin(&request(&[("origin", "https://example.com")])));
+		assert!(!same_origin(&request(&[("origin", "null")])));
+		assert!(!same_origin(&request(&[("sec-fetch-site", "cross-site")])));
+		assert!(!same_origin(&request(&[("origin", "http://localhost:8080"), ("sec-fetch-site", "same-site")])));
+	}
+}
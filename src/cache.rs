@@ -0,0 +1,80 @@
+use crate::{
+	dist,
+	server::{self, Settings},
+};
+use actix_web::{
+	body::MessageBody,
+	dev::{ServiceRequest, ServiceResponse},
+	http::header::{HeaderValue, CACHE_CONTROL},
+	middleware::Next,
+	web,
+};
+use clap::ValueEnum;
+use serde::Deserialize;
+
+/// How browsers may cache the files of a mount.
+#[derive(Clone, Copy, Debug, Deserialize, ValueEnum, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum CachePolicy {
+	/// Leave caching to the browser's heuristics, with ETag and Last-Modified validators
+	Default,
+	/// Never store responses, so a rebuilt bundle is always fetched again
+	NoStore,
+	/// Store responses but check with the server before each use
+	Revalidate,
+	/// Cache responses for a year without checking again
+	Immutable,
+	/// `immutable` for files with a content hash in their name, `revalidate` for everything else
+	Hashed,
+}
+
+const REVALIDATE: &str = "no-cache";
+const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+
+impl CachePolicy {
+	/// Whether the `Files` service should send validators for conditional requests.
+	pub fn uses_validators(self) -> bool {
+		self != CachePolicy::NoStore
+	}
+
+	fn header(self, path: &str) -> Option<&'static str> {
+		match self {
+			CachePolicy::Default => None,
+			CachePolicy::NoStore => Some("no-store"),
+			CachePolicy::Revalidate => Some(REVALIDATE),
+			CachePolicy::Immutable => Some(IMMUTABLE),
+			CachePolicy::Hashed => {
+				let name = path.rsplit('/').next().unwrap_or_default();
+				Some(if dist::is_hashed(name) { IMMUTABLE } else { REVALIDATE })
+			}
+		}
+	}
+}
+
+/// Sets `Cache-Control` on successful responses according to the policy of the mount they come from.
+/// The tools' own endpoints change with every build, so they are never stored.
+pub async fn middleware(
+	req: ServiceRequest,
+	next: Next<impl MessageBody>,
+) -> actix_web::Result<ServiceResponse<impl MessageBody>> {
+	// proxied responses keep whatever caching the backend asked for
+	let policy = match server::is_tools_path(req.path()) {
+		true => Some(CachePolicy::NoStore),
+		false => req
+			.app_data::<web::Data<Settings>>()
+			.filter(|settings| !settings.is_proxied(req.path()))
+			.and_then(|settings| settings.mount_for(req.path()).map(|mount| mount.cache_policy())),
+	};
+	let path = req.path().to_string();
+	let mut res = next.call(req).await?;
+
+	let status = res.status();
+	if !(status.is_success() || status.is_redirection()) || res.headers().contains_key(CACHE_CONTROL) {
+		return Ok(res);
+	}
+	if let Some(value) = policy.and_then(|policy| policy.header(&path)) {
+		res.headers_mut()
+			.insert(CACHE_CONTROL, HeaderValue::from_static(value));
+	}
+	Ok(res)
+}
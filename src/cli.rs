@@ -0,0 +1,344 @@
+use crate::{
+	access,
+	cache::CachePolicy,
+	error::{Error, Result},
+	listing::Listing,
+	proxy::Proxy,
+	server::{normalize_prefix, Settings},
+	throttle::{self, Throttle},
+	wasm_pack::{BuildSettings, Profile},
+};
+use clap::{ArgAction, Args, Parser, Subcommand};
+use std::path::PathBuf;
+
+pub const TEMPLATE_URL: &str = "https://github.com/thisminute/cui-app-template.git";
+
+#[derive(Parser)]
+#[command(
+	name = "cui-tools",
+	version,
+	about = "Build apps with CUI!",
+	after_help = "Exit codes:\n  0  success\n  1  unexpected failure (I/O, server)\n  2  invalid usage\n  3  the app failed to build\n  4  a required tool is missing\n  5  invalid cui.toml"
+)]
+pub struct Cli {
+	/// Print more output, repeat for even more
+	#[arg(short, long, global = true, action = ArgAction::Count)]
+	pub verbose: u8,
+
+	/// Only print warnings and errors
+	#[arg(short, long, global = true, conflicts_with = "verbose")]
+	pub quiet: bool,
+
+	/// Run as if cui-tools was started in this directory
+	#[arg(short = 'C', long = "dir", global = true, value_name = "PATH")]
+	pub dir: Option<PathBuf>,
+
+	/// Config file to use instead of searching for cui.toml
+	#[arg(long, global = true, env = "CUI_CONFIG", value_name = "PATH")]
+	pub config: Option<PathBuf>,
+
+	/// Profile from the config file to apply
+	#[arg(long, global = true, env = "CUI_PROFILE", value_name = "NAME")]
+	pub profile: Option<String>,
+
+	#[command(subcommand)]
+	pub command: Command,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+	/// Build the app, start the development server and rebuild on changes
+	Dev(DevArgs),
+	/// Build the app with wasm-pack
+	Build(BuildArgs),
+	/// Serve the app without building it
+	Serve(ServeArgs),
+	/// Create a new app from the template
+	New(NewArgs),
+	/// Check that the required tools are installed
+	Doctor,
+	/// Remove build output
+	Clean,
+}
+
+#[derive(Args)]
+pub struct BuildArgs {
+	/// Build with optimizations
+	#[arg(long, conflicts_with = "dev")]
+	pub release: bool,
+
+	/// Build without optimizations
+	#[arg(long)]
+	pub dev: bool,
+
+	/// wasm-pack target to build for [default: web]
+	#[arg(long)]
+	pub target: Option<String>,
+
+	/// Comma separated list of app features to enable
+	#[arg(long, value_delimiter = ',')]
+	pub features: Option<Vec<String>>,
+
+	/// Directory to put the deployable release build in [default: dist]
+	#[arg(long, value_name = "PATH")]
+	pub dist_dir: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct DevArgs {
+	#[command(flatten)]
+	pub server: ServerArgs,
+
+	/// Don't rebuild the app when its sources change
+	#[arg(long)]
+	pub no_watch: bool,
+
+	/// Don't print the browser console in the terminal
+	#[arg(long)]
+	pub no_console: bool,
+
+	/// Show build output, requests and the browser console in a full-screen interface
+	#[arg(long)]
+	pub tui: bool,
+}
+
+#[derive(Args)]
+pub struct ServeArgs {
+	#[command(flatten)]
+	pub server: ServerArgs,
+
+	/// Serve the output of `build --release` instead of the app's build directories
+	#[arg(long)]
+	pub dist: bool,
+}
+
+#[derive(Args)]
+pub struct ServerArgs {
+	/// Address to bind the server to [default: 127.0.0.1]
+	#[arg(long, env = "CUI_HOST")]
+	pub host: Option<String>,
+
+	/// Listen on every network interface, so phones and other machines can connect
+	#[arg(long)]
+	pub lan: bool,
+
+	/// Require this token from other machines, a random one when left empty
+	#[arg(long, env = "CUI_TOKEN", value_name = "TOKEN", num_args = 0..=1, default_missing_value = "")]
+	pub token: Option<String>,
+
+	/// Require this user name and password from other machines
+	#[arg(long, env = "CUI_BASIC_AUTH", value_name = "USER:PASSWORD", value_parser = access::parse_basic_auth)]
+	pub basic_auth: Option<String>,
+
+	/// Port to bind the server to [default: 8080]
+	#[arg(short, long, env = "CUI_PORT")]
+	pub port: Option<u16>,
+
+	/// Directory with the wasm-pack output [default: ./app/pkg]
+	#[arg(long, env = "CUI_PKG_DIR", value_name = "PATH")]
+	pub pkg_dir: Option<PathBuf>,
+
+	/// URL prefix to serve the wasm-pack output at [default: /cui]
+	#[arg(long, env = "CUI_PKG_PREFIX", value_name = "PREFIX")]
+	pub pkg_prefix: Option<String>,
+
+	/// Caching policy for the wasm-pack output
+	#[arg(long, value_enum, value_name = "POLICY")]
+	pub pkg_cache: Option<CachePolicy>,
+
+	/// What directories of the wasm-pack output without an index.html show [default: on]
+	#[arg(long, value_enum, value_name = "LISTING")]
+	pub pkg_listing: Option<Listing>,
+
+	/// Directory with the generated HTML [default: ./app/target/html]
+	#[arg(long, env = "CUI_HTML_DIR", value_name = "PATH")]
+	pub html_dir: Option<PathBuf>,
+
+	/// URL prefix to serve the generated HTML at [default: /]
+	#[arg(long, env = "CUI_HTML_PREFIX", value_name = "PREFIX")]
+	pub html_prefix: Option<String>,
+
+	/// Caching policy for the generated HTML
+	#[arg(long, value_enum, value_name = "POLICY")]
+	pub html_cache: Option<CachePolicy>,
+
+	/// What directories of the generated HTML without an index.html show [default: off]
+	#[arg(long, value_enum, value_name = "LISTING")]
+	pub html_listing: Option<Listing>,
+
+	/// Serve over HTTPS, with a generated self-signed certificate unless --cert and --key are given
+	#[arg(long)]
+	pub https: bool,
+
+	/// PEM certificate chain to serve HTTPS with, implies --https
+	#[arg(long, env = "CUI_CERT", value_name = "PATH", requires = "key")]
+	pub cert: Option<PathBuf>,
+
+	/// PEM private key of the certificate
+	#[arg(long, env = "CUI_KEY", value_name = "PATH", requires = "cert")]
+	pub key: Option<PathBuf>,
+
+	/// Don't compress responses on the fly
+	#[arg(long)]
+	pub no_compress: bool,
+
+	/// Serve index.html for unknown paths without a file extension, for client-side routing
+	#[arg(long)]
+	pub spa: bool,
+
+	/// Delay every response by this many milliseconds
+	#[arg(long, value_name = "MS")]
+	pub latency: Option<u64>,
+
+	/// Send response bodies at this many kilobits per second
+	#[arg(long, value_name = "KBPS")]
+	pub bandwidth: Option<u64>,
+
+	/// Fail this share of requests, from 0 to 1, with 503 Service Unavailable
+	#[arg(long, value_name = "RATE", value_parser = throttle::parse_rate)]
+	pub failure_rate: Option<f64>,
+
+	/// Send Cross-Origin-Opener-Policy and Cross-Origin-Embedder-Policy headers, for wasm threads
+	#[arg(long)]
+	pub cross_origin_isolation: bool,
+
+	/// Directory of JSON/YAML fixtures to answer matching requests with [default: ./mocks]
+	#[arg(long, env = "CUI_MOCKS_DIR", value_name = "PATH")]
+	pub mocks_dir: Option<PathBuf>,
+
+	/// Forward requests under PREFIX to a backend at URL, can be repeated
+	#[arg(long, value_name = "PREFIX=URL")]
+	pub proxy: Vec<String>,
+
+	/// Extra response header, can be repeated
+	#[arg(short = 'H', long = "header", value_name = "NAME: VALUE")]
+	pub headers: Vec<String>,
+}
+
+#[derive(Args)]
+pub struct NewArgs {
+	/// Directory to create the app in
+	pub path: PathBuf,
+
+	/// Git repository to use as the app template
+	#[arg(long, default_value = TEMPLATE_URL)]
+	pub template: String,
+}
+
+impl ServerArgs {
+	pub fn apply(self, settings: &mut Settings) -> Result<()> {
+		if self.lan {
+			settings.host = "0.0.0.0".into();
+		}
+		if let Some(host) = self.host {
+			settings.host = host;
+		}
+		if let Some(token) = self.token {
+			settings.token = Some(match token.is_empty() {
+				true => access::generate_token(),
+				false => token,
+			});
+		}
+		if let Some(credentials) = self.basic_auth {
+			settings.basic_auth = Some(credentials);
+		}
+		if let Some(port) = self.port {
+			settings.port = port;
+		}
+		if let Some(dir) = self.pkg_dir {
+			settings.pkg.dir = dir;
+		}
+		if let Some(prefix) = self.pkg_prefix {
+			settings.pkg.prefix = normalize_prefix(&prefix);
+		}
+		if let Some(policy) = self.pkg_cache {
+			settings.pkg.cache = Some(policy);
+		}
+		if let Some(dir) = self.html_dir {
+			settings.html.dir = dir;
+		}
+		if let Some(prefix) = self.html_prefix {
+			settings.html.prefix = normalize_prefix(&prefix);
+		}
+		if let Some(policy) = self.html_cache {
+			settings.html.cache = Some(policy);
+		}
+		if let Some(listing) = self.pkg_listing {
+			settings.pkg.listing = listing;
+		}
+		if let Some(listing) = self.html_listing {
+			settings.html.listing = listing;
+		}
+		if self.https {
+			settings.https = true;
+		}
+		if let (Some(cert), Some(key)) = (self.cert, self.key) {
+			settings.https = true;
+			settings.cert = Some(cert);
+			settings.key = Some(key);
+		}
+		if self.no_compress {
+			settings.compress = false;
+		}
+		if self.spa {
+			settings.spa = true;
+		}
+		if self.cross_origin_isolation {
+			settings.cross_origin_isolation = true;
+		}
+		if self.latency.is_some() || self.bandwidth.is_some() || self.failure_rate.is_some() {
+			// ahead of the configured rules, so the flags apply everywhere
+			let rule = Throttle::everywhere(self.latency.unwrap_or(0), self.bandwidth, self.failure_rate.unwrap_or(0.0));
+			settings.throttle.insert(0, rule);
+		}
+		if let Some(dir) = self.mocks_dir {
+			settings.mocks_dir = Some(dir);
+		}
+		for proxy in self.proxy {
+			settings.proxies.push(Proxy::parse(&proxy).map_err(Error::Usage)?);
+		}
+		for header in self.headers {
+			let (name, value) = header
+				.split_once(':')
+				.ok_or_else(|| Error::Usage(format!("expected `NAME: VALUE`, found `{}`", header)))?;
+			settings.headers.insert(name.trim().into(), value.trim().into());
+		}
+		Ok(())
+	}
+}
+
+impl BuildArgs {
+	pub fn apply(self, settings: &mut BuildSettings) {
+		if self.release {
+			settings.profile = Profile::Release;
+		}
+		if self.dev {
+			settings.profile = Profile::Dev;
+		}
+		if let Some(target) = self.target {
+			settings.target = target;
+		}
+		if let Some(features) = self.features {
+			settings.features = features;
+		}
+		if let Some(dist_dir) = self.dist_dir {
+			settings.dist_dir = dist_dir;
+		}
+	}
+}
+
+impl Cli {
+	/// Whether the output goes to the interface of `dev --tui`.
+	pub fn tui(&self) -> bool {
+		matches!(&self.command, Command::Dev(args) if args.tui)
+	}
+
+	pub fn log_level(&self) -> &'static str {
+		match (self.quiet, self.verbose) {
+			(true, _) => "warn",
+			(false, 0) => "info",
+			(false, 1) => "debug",
+			(false, _) => "trace",
+		}
+	}
+}
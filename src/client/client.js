@@ -0,0 +1,211 @@
+// Injected into every page served by `cui-tools dev`.
+
+const RETRY_MS = 1000;
+const OVERLAY_ID = "__cui-overlay";
+const CONSOLE_PATH = "/__cui/console";
+const FLUSH_MS = 100;
+const MAX_MESSAGE = 10000;
+const LEVELS = ["log", "info", "warn", "error", "debug"];
+
+const OVERLAY_STYLE = `
+	:host {
+		position: fixed;
+		inset: 0;
+		z-index: 2147483647;
+		overflow: auto;
+		background: rgba(20, 20, 20, 0.96);
+		color: #e8e8e8;
+		font: 14px/1.5 ui-monospace, SFMono-Regular, Menlo, Consolas, monospace;
+	}
+	main {
+		max-width: 960px;
+		margin: 0 auto;
+		padding: 32px;
+	}
+	h1 {
+		margin: 0 0 8px;
+		color: #ff6b6b;
+		font-size: 20px;
+	}
+	.summary {
+		margin: 0 0 24px;
+		color: #aaa;
+	}
+	section {
+		margin-bottom: 24px;
+		border-left: 3px solid #ff6b6b;
+		padding-left: 16px;
+	}
+	.location {
+		color: #6bc5ff;
+	}
+	.message {
+		font-weight: bold;
+	}
+	.suggestion {
+		margin-top: 8px;
+		color: #8fd18f;
+	}
+	pre {
+		margin: 8px 0 0;
+		white-space: pre-wrap;
+		color: #ccc;
+	}
+`;
+
+function element(tag, className, text) {
+	const node = document.createElement(tag);
+	if (className) {
+		node.className = className;
+	}
+	if (text) {
+		node.textContent = text;
+	}
+	return node;
+}
+
+function showOverlay(message, diagnostics) {
+	hideOverlay();
+
+	const host = element("div");
+	host.id = OVERLAY_ID;
+	const root = host.attachShadow({ mode: "open" });
+	const style = element("style");
+	style.textContent = OVERLAY_STYLE;
+	root.appendChild(style);
+
+	const main = element("main");
+	main.appendChild(element("h1", null, "Build failed"));
+	main.appendChild(element("p", "summary", message));
+	for (const diagnostic of diagnostics.filter((diagnostic) => diagnostic.severity === "error")) {
+		const section = element("section");
+		const span = diagnostic.spans.find((span) => span.is_primary) ?? diagnostic.spans[0];
+		if (span) {
+			section.appendChild(element("div", "location", `${span.file}:${span.line_start}:${span.column_start}`));
+		}
+		section.appendChild(element("div", "message", diagnostic.message));
+		section.appendChild(element("pre", null, diagnostic.rendered));
+		for (const suggestion of diagnostic.suggestions) {
+			section.appendChild(
+				element("div", "suggestion", `${suggestion.message}: \`${suggestion.replacement}\``),
+			);
+		}
+		main.appendChild(section);
+	}
+	root.appendChild(main);
+	document.documentElement.appendChild(host);
+}
+
+function hideOverlay() {
+	document.getElementById(OVERLAY_ID)?.remove();
+}
+
+function connect(reconnecting) {
+	const protocol = location.protocol === "https:" ? "wss:" : "ws:";
+	const socket = new WebSocket(`${protocol}//${location.host}/__cui/ws`);
+
+	socket.addEventListener("open", () => {
+		// the server restarted while we were away, the page may be stale
+		if (reconnecting) {
+			location.reload();
+		}
+	});
+
+	socket.addEventListener("message", (event) => {
+		const message = JSON.parse(event.data);
+		switch (message.type) {
+			case "reload":
+				hideOverlay();
+				location.reload();
+				break;
+			case "build-error":
+				console.error(`[cui] build failed\n${message.message}`);
+				showOverlay(message.message, message.diagnostics);
+				break;
+		}
+	});
+
+	socket.addEventListener("close", () => {
+		setTimeout(() => connect(true), RETRY_MS);
+	});
+}
+
+// Identifies the tab in the terminal, kept across reloads.
+function tabId() {
+	const random = () => Math.random().toString(36).slice(2, 6);
+	try {
+		let id = sessionStorage.getItem("__cui-tab");
+		if (!id) {
+			id = random();
+			sessionStorage.setItem("__cui-tab", id);
+		}
+		return id;
+	} catch {
+		return random();
+	}
+}
+
+function format(value) {
+	if (typeof value === "string") {
+		return value;
+	}
+	if (value instanceof Error) {
+		return value.stack || String(value);
+	}
+	try {
+		return JSON.stringify(value) ?? String(value);
+	} catch {
+		return String(value);
+	}
+}
+
+function forwardConsole() {
+	const tab = tabId();
+	let pending = [];
+
+	const flush = (leaving) => {
+		if (pending.length === 0) {
+			return;
+		}
+		const body = JSON.stringify({ tab, entries: pending });
+		pending = [];
+		if (leaving) {
+			navigator.sendBeacon(CONSOLE_PATH, body);
+		} else {
+			fetch(CONSOLE_PATH, { method: "POST", body, keepalive: true }).catch(() => {});
+		}
+	};
+	const forward = (level, args) => {
+		const message = args.map(format).join(" ");
+		// our own messages are about the server, which knows already
+		if (message.startsWith("[cui]")) {
+			return;
+		}
+		pending.push({ level, message: message.slice(0, MAX_MESSAGE) });
+		if (pending.length === 1) {
+			setTimeout(() => flush(false), FLUSH_MS);
+		}
+	};
+
+	// wasm panics arrive through console.error, by way of console_error_panic_hook
+	for (const level of LEVELS) {
+		const original = console[level];
+		console[level] = (...args) => {
+			original.apply(console, args);
+			forward(level, args);
+		};
+	}
+	addEventListener("error", (event) => {
+		const where = event.filename ? ` (${event.filename}:${event.lineno}:${event.colno})` : "";
+		forward("error", [`uncaught ${format(event.error ?? event.message)}${where}`]);
+	});
+	addEventListener("unhandledrejection", (event) => {
+		forward("error", ["unhandled rejection:", event.reason]);
+	});
+	addEventListener("pagehide", () => flush(true));
+}
+
+if (FORWARD_CONSOLE) {
+	forwardConsole();
+}
+connect(false);
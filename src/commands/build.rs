@@ -0,0 +1,25 @@
+use crate::{
+	cli::BuildArgs,
+	config::Config,
+	dist,
+	error::Result,
+	wasm_pack::{self, Profile},
+};
+use actix_web::rt::System;
+
+pub fn run(config: Config, args: BuildArgs) -> Result<()> {
+	let mut settings = config.build_settings();
+	args.apply(&mut settings);
+	if settings.profile == Profile::Release {
+		dist::check_dir(&settings.dist_dir, &config.server_settings(), &[&config.root, &settings.app_dir])?;
+	}
+
+	System::new().block_on(wasm_pack::build(&settings))?;
+
+	if settings.profile == Profile::Release {
+		let files = dist::assemble(&config.server_settings(), &settings.dist_dir)?;
+		log::info!("wrote the release build to {}", settings.dist_dir.display());
+		dist::print_summary(&settings.dist_dir, &files);
+	}
+	Ok(())
+}
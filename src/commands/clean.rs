@@ -0,0 +1,20 @@
+use crate::{config::Config, dist, error::Result};
+use std::{fs, io::ErrorKind};
+
+pub fn run(config: Config) -> Result<()> {
+	let settings = config.build_settings();
+	let mut dirs = vec![settings.app_dir.join("pkg"), settings.app_dir.join("target")];
+	// a `dist_dir` that `build --release` refuses to empty is left alone here too
+	match dist::check_dir(&settings.dist_dir, &config.server_settings(), &[&config.root, &settings.app_dir]) {
+		Ok(()) => dirs.push(settings.dist_dir.clone()),
+		Err(error) => log::warn!("not removing {}: {}", settings.dist_dir.display(), error),
+	}
+	for dir in &dirs {
+		match fs::remove_dir_all(dir) {
+			Ok(()) => log::info!("removed {}", dir.display()),
+			Err(error) if error.kind() == ErrorKind::NotFound => {}
+			Err(error) => return Err(error.into()),
+		}
+	}
+	Ok(())
+}
@@ -0,0 +1,53 @@
+use crate::{
+	cli::DevArgs,
+	config::Config,
+	dev_server::DevServer,
+	error::Result,
+	tui::{self, Action, Tui},
+};
+use actix_web::rt::System;
+
+pub fn run(config: Config, args: DevArgs) -> Result<()> {
+	let mut settings = config.server_settings();
+	args.server.apply(&mut settings)?;
+	if args.no_console {
+		settings.forward_console = false;
+	}
+	let mut watch = config.watch_settings();
+	if args.no_watch {
+		watch.enabled = false;
+	}
+	let url = format!(
+		"{}://{}:{}",
+		if settings.https { "https" } else { "http" },
+		settings.host,
+		settings.port
+	);
+	let mut server = DevServer::with_settings(settings)
+		.build(config.build_settings())
+		.watch(watch);
+	if args.tui {
+		server = server.on_event(tui::show_event);
+	}
+
+	let mut tui = match args.tui {
+		true => Some(Tui::start(url)?),
+		false => None,
+	};
+	System::new().block_on(async move {
+		let mut server = server.start().await?;
+		let tui = match &mut tui {
+			Some(tui) => tui,
+			None => return server.wait().await,
+		};
+		loop {
+			tokio::select! {
+				result = server.wait() => return result,
+				action = tui.next() => match action {
+					Action::Rebuild => server.rebuild(),
+					Action::Quit => return server.shutdown().await,
+				},
+			}
+		}
+	})
+}
@@ -0,0 +1,63 @@
+use crate::{
+	config::{self, Config},
+	error::{Error, Result},
+};
+use std::process::Command;
+
+const WASM_TARGET: &str = "wasm32-unknown-unknown";
+
+pub fn run(config: Config) -> Result<()> {
+	let mut missing = Vec::new();
+
+	for tool in &["rustc", "cargo", "wasm-pack"] {
+		match version(tool) {
+			Some(version) => println!("ok       {}", version),
+			None => {
+				println!("missing  {}", tool);
+				missing.push(tool.to_string());
+			}
+		}
+	}
+
+	let targets = Command::new("rustup")
+		.args(["target", "list", "--installed"])
+		.output()
+		.ok()
+		.map(|output| String::from_utf8_lossy(&output.stdout).into_owned());
+	match targets {
+		Some(targets) if targets.lines().any(|line| line.trim() == WASM_TARGET) => {
+			println!("ok       target {}", WASM_TARGET)
+		}
+		_ => {
+			println!("missing  target {} (rustup target add {})", WASM_TARGET, WASM_TARGET);
+			missing.push(WASM_TARGET.to_string());
+		}
+	}
+
+	match &config.path {
+		Some(path) => println!("ok       config at {}", path.display()),
+		None => println!("default  no {} found, using default settings", config::FILE_NAME),
+	}
+
+	let manifest = config.build_settings().app_dir.join("Cargo.toml");
+	if manifest.is_file() {
+		println!("ok       app at {}", manifest.display());
+	} else {
+		println!("missing  app at {} (try `cui-tools new`)", manifest.display());
+		missing.push(manifest.display().to_string());
+	}
+
+	if missing.is_empty() {
+		Ok(())
+	} else {
+		Err(Error::MissingTool(missing.join(", ")))
+	}
+}
+
+fn version(tool: &str) -> Option<String> {
+	let output = Command::new(tool).arg("--version").output().ok()?;
+	if !output.status.success() {
+		return None;
+	}
+	Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
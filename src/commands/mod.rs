@@ -0,0 +1,28 @@
+mod build;
+mod clean;
+mod dev;
+mod doctor;
+mod new;
+mod serve;
+
+use crate::{
+	cli::{Cli, Command},
+	config::Config,
+	error::Result,
+};
+
+pub fn run(cli: Cli) -> Result<()> {
+	if let Command::New(args) = cli.command {
+		return new::run(args);
+	}
+
+	let config = Config::load(cli.config.as_deref(), cli.profile.as_deref())?;
+	match cli.command {
+		Command::Dev(args) => dev::run(config, args),
+		Command::Build(args) => build::run(config, args),
+		Command::Serve(args) => serve::run(config, args),
+		Command::Doctor => doctor::run(config),
+		Command::Clean => clean::run(config),
+		Command::New(_) => unreachable!(),
+	}
+}
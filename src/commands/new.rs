@@ -0,0 +1,27 @@
+use crate::{
+	cli::NewArgs,
+	config,
+	error::{Error, Result},
+};
+use std::{fs, process::Command};
+
+pub fn run(args: NewArgs) -> Result<()> {
+	if fs::read_dir(&args.path).is_ok_and(|mut entries| entries.next().is_some()) {
+		return Err(Error::Usage(format!("{} already exists and is not empty", args.path.display())));
+	}
+
+	let app_dir = args.path.join("app");
+	log::info!("cloning {} into {}...", args.template, app_dir.display());
+	let status = Command::new("git")
+		.args(["clone", "--depth", "1", &args.template])
+		.arg(&app_dir)
+		.status()
+		.map_err(|_| Error::MissingTool("git".into()))?;
+	if !status.success() {
+		return Err(Error::Other(format!("git clone exited with {}", status)));
+	}
+
+	fs::write(args.path.join(config::FILE_NAME), config::TEMPLATE)?;
+	log::info!("created a new app, run `cui-tools -C {} dev` to start it", args.path.display());
+	Ok(())
+}
@@ -0,0 +1,24 @@
+use crate::{
+	cache::CachePolicy,
+	cli::ServeArgs,
+	config::Config,
+	error::Result,
+	events::Events,
+	server,
+};
+use actix_web::rt::System;
+
+pub fn run(config: Config, args: ServeArgs) -> Result<()> {
+	let mut settings = config.server_settings();
+	if args.dist {
+		let dist_dir = config.build_settings().dist_dir;
+		for mount in [&mut settings.pkg, &mut settings.html] {
+			mount.dir = dist_dir.join(mount.prefix.trim_start_matches('/'));
+			mount.cache.get_or_insert(CachePolicy::Hashed);
+		}
+	}
+	args.server.apply(&mut settings)?;
+
+	System::new().block_on(server::run(settings, Events::new()))?;
+	Ok(())
+}
@@ -0,0 +1,163 @@
+use crate::server::Settings;
+use actix_files::NamedFile;
+use actix_web::{
+	body::{BoxBody, MessageBody},
+	dev::{ServiceRequest, ServiceResponse},
+	http::{
+		header::{self, ContentEncoding, HeaderValue},
+		Method,
+	},
+	middleware::Next,
+	web,
+};
+use flate2::{write::GzEncoder, Compression};
+use std::{
+	fs,
+	io::{self, Write},
+	path::{Path, PathBuf},
+};
+
+/// Extensions of files that get precompressed siblings.
+pub const EXTENSIONS: &[&str] = &["wasm", "js", "mjs", "css", "html", "svg", "json"];
+
+/// Sizes of the siblings written next to a file, `None` where compressing did not make it smaller.
+pub struct Sizes {
+	pub gzip: Option<u64>,
+	pub brotli: Option<u64>,
+}
+
+/// Writes `<path>.gz` and `<path>.br` with the best compression either format offers.
+/// A sibling that would not be smaller than the file is left out, so the file itself is served.
+pub fn write_siblings(path: &Path) -> io::Result<Sizes> {
+	let content = fs::read(path)?;
+
+	let mut gzip = GzEncoder::new(Vec::new(), Compression::best());
+	gzip.write_all(&content)?;
+	let gzip = gzip.finish()?;
+
+	let mut brotli = Vec::new();
+	let params = brotli::enc::BrotliEncoderParams {
+		quality: 11,
+		..Default::default()
+	};
+	brotli::BrotliCompress(&mut content.as_slice(), &mut brotli, &params)?;
+
+	Ok(Sizes {
+		gzip: write_smaller(&sibling(path, "gz"), &gzip, content.len())?,
+		brotli: write_smaller(&sibling(path, "br"), &brotli, content.len())?,
+	})
+}
+
+fn write_smaller(path: &Path, compressed: &[u8], original: usize) -> io::Result<Option<u64>> {
+	if compressed.len() >= original {
+		// a sibling from an earlier run would still be served
+		return match fs::remove_file(path) {
+			Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error),
+			_ => Ok(None),
+		};
+	}
+	fs::write(path, compressed)?;
+	Ok(Some(compressed.len() as u64))
+}
+
+pub fn is_compressible(path: &Path) -> bool {
+	path.extension()
+		.and_then(|extension| extension.to_str())
+		.is_some_and(|extension| EXTENSIONS.contains(&extension))
+}
+
+/// Answers requests for files that have a smaller `.br` or `.gz` sibling with the sibling,
+/// when the client accepts that encoding. Everything else falls through to the `Files` services.
+/// Range requests do too, their byte offsets are into the file and not the sibling.
+pub async fn middleware(
+	req: ServiceRequest,
+	next: Next<impl MessageBody + 'static>,
+) -> actix_web::Result<ServiceResponse<BoxBody>> {
+	if (req.method() != Method::GET && req.method() != Method::HEAD) || req.headers().contains_key(header::RANGE) {
+		return next.call(req).await.map(ServiceResponse::map_into_boxed_body);
+	}
+	let settings = req.app_data::<web::Data<Settings>>().cloned();
+	let file = settings.and_then(|settings| resolve(&settings, req.path()));
+	let encodings = req
+		.headers()
+		.get(header::ACCEPT_ENCODING)
+		.and_then(|value| value.to_str().ok())
+		.map(accepted)
+		.unwrap_or_default();
+
+	let found = file.and_then(|file| {
+		encodings.into_iter().find_map(|(encoding, extension)| {
+			let compressed = sibling(&file, extension);
+			is_smaller(&compressed, &file).then(|| (file.clone(), compressed, encoding))
+		})
+	});
+	let (file, compressed, encoding) = match found {
+		Some(found) => found,
+		None => return next.call(req).await.map(ServiceResponse::map_into_boxed_body),
+	};
+
+	let named = NamedFile::open_async(&compressed)
+		.await?
+		.set_content_type(mime_guess::from_path(&file).first_or_octet_stream())
+		.set_content_encoding(encoding)
+		.disable_content_disposition();
+	let (req, _) = req.into_parts();
+	let mut res = named.into_response(&req);
+	res.headers_mut()
+		.append(header::VARY, HeaderValue::from_static("accept-encoding"));
+	Ok(ServiceResponse::new(req, res))
+}
+
+fn is_smaller(compressed: &Path, file: &Path) -> bool {
+	match (fs::metadata(compressed), fs::metadata(file)) {
+		(Ok(compressed), Ok(file)) => compressed.is_file() && compressed.len() < file.len(),
+		_ => false,
+	}
+}
+
+/// The file to look for siblings of.
+fn resolve(settings: &Settings, path: &str) -> Option<PathBuf> {
+	// a proxy answers for the files under its prefix
+	if settings.is_proxied(path) {
+		return None;
+	}
+	let file = settings.file_for(path)?;
+	// dev pages need the live reload script injected, which only works on the uncompressed page
+	if settings.live_reload && file.extension().is_some_and(|extension| extension == "html") {
+		return None;
+	}
+	Some(file)
+}
+
+/// The encodings the client accepts that we have siblings for, best first.
+fn accepted(header: &str) -> Vec<(ContentEncoding, &'static str)> {
+	let accepts = |name: &str| {
+		header.split(',').any(|part| {
+			let mut params = part.split(';');
+			let coding = params.next().unwrap_or("").trim();
+			let refused = params.any(|param| {
+				param
+					.trim()
+					.strip_prefix("q=")
+					.and_then(|q| q.parse::<f32>().ok())
+					== Some(0.0)
+			});
+			coding.eq_ignore_ascii_case(name) && !refused
+		})
+	};
+	let mut encodings = Vec::new();
+	if accepts("br") {
+		encodings.push((ContentEncoding::Brotli, "br"));
+	}
+	if accepts("gzip") {
+		encodings.push((ContentEncoding::Gzip, "gz"));
+	}
+	encodings
+}
+
+fn sibling(path: &Path, extension: &str) -> PathBuf {
+	let mut name = path.as_os_str().to_owned();
+	name.push(".");
+	name.push(extension);
+	PathBuf::from(name)
+}
@@ -0,0 +1,493 @@
+use crate::{
+	cache::CachePolicy,
+	error::{Error, Result},
+	headers::HeaderRule,
+	listing::Listing,
+	mocks,
+	pattern::PathPattern,
+	proxy::Proxy,
+	redirects::Redirect,
+	throttle::Throttle,
+	tls,
+	server::{self, normalize_prefix},
+	wasm_pack::{self, BuildSettings},
+	watch::WatchSettings,
+};
+use serde::Deserialize;
+use std::{
+	collections::BTreeMap,
+	env, fs,
+	path::{Path, PathBuf},
+	time::Duration,
+};
+
+pub const FILE_NAME: &str = "cui.toml";
+
+/// The contents of a `cui.toml`, with the selected profile already merged in.
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+	pub server: ServerConfig,
+	pub build: BuildConfig,
+	pub watch: WatchConfig,
+	pub profiles: BTreeMap<String, Profile>,
+
+	/// The file this config was read from, if any.
+	#[serde(skip)]
+	pub path: Option<PathBuf>,
+
+	/// Directory relative paths in the file are resolved against.
+	#[serde(skip)]
+	pub root: PathBuf,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+	pub server: ServerConfig,
+	pub build: BuildConfig,
+	pub watch: WatchConfig,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+	pub host: Option<String>,
+	pub port: Option<u16>,
+	pub pkg: MountConfig,
+	pub html: MountConfig,
+	pub headers: BTreeMap<String, String>,
+	pub header_rules: Vec<HeaderRule>,
+	pub headers_file: Option<bool>,
+	pub mocks_dir: Option<PathBuf>,
+	pub proxy: Vec<Proxy>,
+	pub redirects: Vec<Redirect>,
+	pub redirects_file: Option<bool>,
+	pub spa: Option<bool>,
+	pub spa_exclude: Vec<PathPattern>,
+	pub throttle: Vec<Throttle>,
+	pub token: Option<String>,
+	pub basic_auth: Option<String>,
+	pub https: Option<bool>,
+	pub cert: Option<PathBuf>,
+	pub key: Option<PathBuf>,
+	pub forward_console: Option<bool>,
+	pub compress: Option<bool>,
+	pub cross_origin_isolation: Option<bool>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MountConfig {
+	pub dir: Option<PathBuf>,
+	pub prefix: Option<String>,
+	pub cache: Option<CachePolicy>,
+	pub listing: Option<Listing>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BuildConfig {
+	pub app_dir: Option<PathBuf>,
+	pub target: Option<String>,
+	pub profile: Option<wasm_pack::Profile>,
+	pub features: Option<Vec<String>>,
+	pub dist_dir: Option<PathBuf>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WatchConfig {
+	pub enabled: Option<bool>,
+	pub debounce_ms: Option<u64>,
+	pub paths: Option<Vec<PathBuf>>,
+}
+
+pub const TEMPLATE: &str = r#"# Settings for cui-tools, command line flags take precedence over this file.
+
+[server]
+host = "127.0.0.1"
+port = 8080
+# other machines on the network need this token (open the printed link) or password,
+# set `host = "0.0.0.0"` or pass --lan to let them connect
+# token = "..."
+# basic_auth = "user:password"
+# serve over TLS, with `cert` and `key` when set and a generated self-signed certificate otherwise
+https = false
+# cert = "certs/localhost.pem"
+# key = "certs/localhost-key.pem"
+# print the browser console of pages opened from `cui-tools dev` in the terminal
+forward_console = true
+# compress responses that don't have a precompressed .br or .gz sibling
+compress = true
+# send COOP/COEP headers so the app can use SharedArrayBuffer and wasm threads
+cross_origin_isolation = false
+# serve index.html for unknown paths so the app can route on the client,
+# except for paths matching `spa_exclude`
+spa = false
+# spa_exclude = ["/api/*"]
+# JSON/YAML fixtures that answer matching requests, ahead of the proxies and mounts
+mocks_dir = "mocks"
+
+[server.pkg]
+dir = "app/pkg"
+prefix = "/cui"
+# what directories without an index.html show: "off", "on", "json" or "styled"
+listing = "on"
+
+[server.html]
+dir = "app/target/html"
+prefix = "/"
+listing = "off"
+# one of "default", "no-store", "revalidate", "immutable" or "hashed", when left out
+# `dev` uses "no-store", `serve --dist` uses "hashed" and `serve` uses "default"
+# cache = "revalidate"
+
+[build]
+app_dir = "app"
+target = "web"
+profile = "dev"
+# `cui-tools build --release` puts a deployable copy of the app here
+dist_dir = "dist"
+
+# `cui-tools dev` rebuilds the app when files in these directories change.
+[watch]
+enabled = true
+debounce_ms = 200
+paths = ["app"]
+
+# Headers for paths matching a pattern, `_headers` in the HTML root works too.
+# [[server.header_rules]]
+# for = "/cui/*"
+# values = { Access-Control-Allow-Origin = "*" }
+
+# Forward requests to a backend, WebSockets included.
+# [[server.proxy]]
+# prefix = "/api"
+# target = "http://127.0.0.1:3000"
+# strip_prefix = false
+# headers = { X-Dev-User = "alice" }
+
+# Simulate a slow or unreliable network, the first matching rule applies.
+# [[server.throttle]]
+# for = "/cui/*"
+# latency_ms = 300
+# bandwidth_kbps = 1600
+# failure_rate = 0.05
+
+# Redirects and rewrites (status 200), `_redirects` in the HTML root works too.
+# [[server.redirects]]
+# from = "/blog/:year/*"
+# to = "/posts/:year/:splat"
+# status = 301
+
+# Select a profile with `cui-tools --profile staging ...`.
+[profiles.staging.server]
+port = 8081
+
+[profiles.staging.build]
+profile = "release"
+"#;
+
+impl Config {
+	/// Loads `path`, or the first `cui.toml` found from the current directory upward,
+	/// and merges the named profile over the top level settings.
+	pub fn load(path: Option<&Path>, profile: Option<&str>) -> Result<Self> {
+		Self::load_in(&env::current_dir()?, path, profile)
+	}
+
+	/// [`load`](Self::load), looking for a `cui.toml` from `start` upward.
+	fn load_in(start: &Path, path: Option<&Path>, profile: Option<&str>) -> Result<Self> {
+		let path = match path {
+			Some(path) => Some(path.to_path_buf()),
+			None => discover(start),
+		};
+
+		let mut config = match &path {
+			Some(path) => {
+				log::debug!("reading {}", path.display());
+				let text = fs::read_to_string(path)
+					.map_err(|error| Error::Config(format!("cannot read {}: {}", path.display(), error)))?;
+				let mut config: Config = toml::from_str(&text)
+					.map_err(|error| Error::Config(format!("{}: {}", path.display(), error)))?;
+				config.root = path.parent().map(Path::to_path_buf).unwrap_or_default();
+				config.path = Some(path.clone());
+				config
+			}
+			None => Config::default(),
+		};
+
+		if let Some(name) = profile {
+			let profile = config.profiles.remove(name).ok_or_else(|| {
+				Error::Config(match &path {
+					Some(path) => format!("profile `{}` is not defined in {}", name, path.display()),
+					None => format!("profile `{}` was selected but no {} was found", name, FILE_NAME),
+				})
+			})?;
+			config.server.merge(profile.server);
+			config.build.merge(profile.build);
+			config.watch.merge(profile.watch);
+		}
+		Ok(config)
+	}
+
+	pub fn server_settings(&self) -> server::Settings {
+		let mut settings = server::Settings::default();
+		settings.pkg.dir = self.root.join(&settings.pkg.dir);
+		settings.html.dir = self.root.join(&settings.html.dir);
+		settings.mocks_dir = Some(self.root.join(mocks::DIR));
+		settings.cert_dir = self.root.join(tls::CERT_DIR);
+
+		let server = &self.server;
+		if let Some(host) = &server.host {
+			settings.host = host.clone();
+		}
+		if let Some(port) = server.port {
+			settings.port = port;
+		}
+		if let Some(dir) = &server.pkg.dir {
+			settings.pkg.dir = self.root.join(dir);
+		}
+		if let Some(prefix) = &server.pkg.prefix {
+			settings.pkg.prefix = normalize_prefix(prefix);
+		}
+		merge(&mut settings.pkg.cache, server.pkg.cache);
+		if let Some(listing) = server.pkg.listing {
+			settings.pkg.listing = listing;
+		}
+		if let Some(dir) = &server.html.dir {
+			settings.html.dir = self.root.join(dir);
+		}
+		if let Some(prefix) = &server.html.prefix {
+			settings.html.prefix = normalize_prefix(prefix);
+		}
+		merge(&mut settings.html.cache, server.html.cache);
+		if let Some(listing) = server.html.listing {
+			settings.html.listing = listing;
+		}
+		settings.headers.extend(server.headers.clone());
+		settings.header_rules.extend(server.header_rules.iter().cloned());
+		if let Some(headers_file) = server.headers_file {
+			settings.headers_file = headers_file;
+		}
+		if let Some(dir) = &server.mocks_dir {
+			settings.mocks_dir = Some(self.root.join(dir));
+		}
+		settings.proxies.extend(server.proxy.iter().cloned());
+		settings.redirects.extend(server.redirects.iter().cloned());
+		if let Some(redirects_file) = server.redirects_file {
+			settings.redirects_file = redirects_file;
+		}
+		if let Some(spa) = server.spa {
+			settings.spa = spa;
+		}
+		settings.spa_exclude.extend(server.spa_exclude.iter().cloned());
+		settings.throttle.extend(server.throttle.iter().cloned());
+		merge(&mut settings.token, server.token.clone());
+		merge(&mut settings.basic_auth, server.basic_auth.clone());
+		if let Some(https) = server.https {
+			settings.https = https;
+		}
+		if let Some(cert) = &server.cert {
+			settings.cert = Some(self.root.join(cert));
+		}
+		if let Some(key) = &server.key {
+			settings.key = Some(self.root.join(key));
+		}
+		if let Some(forward) = server.forward_console {
+			settings.forward_console = forward;
+		}
+		if let Some(compress) = server.compress {
+			settings.compress = compress;
+		}
+		if let Some(isolation) = server.cross_origin_isolation {
+			settings.cross_origin_isolation = isolation;
+		}
+		settings
+	}
+
+	pub fn build_settings(&self) -> BuildSettings {
+		let mut settings = BuildSettings::default();
+		settings.app_dir = self.root.join(&settings.app_dir);
+		settings.dist_dir = self.root.join(&settings.dist_dir);
+
+		let build = &self.build;
+		if let Some(app_dir) = &build.app_dir {
+			settings.app_dir = self.root.join(app_dir);
+		}
+		if let Some(target) = &build.target {
+			settings.target = target.clone();
+		}
+		if let Some(profile) = build.profile {
+			settings.profile = profile;
+		}
+		if let Some(features) = &build.features {
+			settings.features = features.clone();
+		}
+		if let Some(dist_dir) = &build.dist_dir {
+			settings.dist_dir = self.root.join(dist_dir);
+		}
+		settings
+	}
+
+	pub fn watch_settings(&self) -> WatchSettings {
+		let mut settings = WatchSettings::default();
+		let watch = &self.watch;
+		if let Some(enabled) = watch.enabled {
+			settings.enabled = enabled;
+		}
+		if let Some(debounce) = watch.debounce_ms {
+			settings.debounce = Duration::from_millis(debounce);
+		}
+		settings.paths = match &watch.paths {
+			Some(paths) => paths.iter().map(|path| self.root.join(path)).collect(),
+			None => vec![self.build_settings().app_dir],
+		};
+		settings
+	}
+}
+
+impl ServerConfig {
+	fn merge(&mut self, other: Self) {
+		merge(&mut self.host, other.host);
+		merge(&mut self.port, other.port);
+		merge(&mut self.pkg.dir, other.pkg.dir);
+		merge(&mut self.pkg.prefix, other.pkg.prefix);
+		merge(&mut self.pkg.cache, other.pkg.cache);
+		merge(&mut self.pkg.listing, other.pkg.listing);
+		merge(&mut self.html.dir, other.html.dir);
+		merge(&mut self.html.prefix, other.html.prefix);
+		merge(&mut self.html.cache, other.html.cache);
+		merge(&mut self.html.listing, other.html.listing);
+		self.headers.extend(other.headers);
+		self.header_rules.extend(other.header_rules);
+		merge(&mut self.headers_file, other.headers_file);
+		merge(&mut self.mocks_dir, other.mocks_dir);
+		// the first matching proxy, redirect and throttle rule wins, so the profile's go first
+		prepend(&mut self.proxy, other.proxy);
+		prepend(&mut self.redirects, other.redirects);
+		merge(&mut self.redirects_file, other.redirects_file);
+		merge(&mut self.spa, other.spa);
+		self.spa_exclude.extend(other.spa_exclude);
+		prepend(&mut self.throttle, other.throttle);
+		merge(&mut self.token, other.token);
+		merge(&mut self.basic_auth, other.basic_auth);
+		merge(&mut self.https, other.https);
+		merge(&mut self.cert, other.cert);
+		merge(&mut self.key, other.key);
+		merge(&mut self.forward_console, other.forward_console);
+		merge(&mut self.compress, other.compress);
+		merge(&mut self.cross_origin_isolation, other.cross_origin_isolation);
+	}
+}
+
+impl WatchConfig {
+	fn merge(&mut self, other: Self) {
+		merge(&mut self.enabled, other.enabled);
+		merge(&mut self.debounce_ms, other.debounce_ms);
+		merge(&mut self.paths, other.paths);
+	}
+}
+
+impl BuildConfig {
+	fn merge(&mut self, other: Self) {
+		merge(&mut self.app_dir, other.app_dir);
+		merge(&mut self.target, other.target);
+		merge(&mut self.profile, other.profile);
+		merge(&mut self.features, other.features);
+		merge(&mut self.dist_dir, other.dist_dir);
+	}
+}
+
+fn merge<T>(value: &mut Option<T>, other: Option<T>) {
+	if other.is_some() {
+		*value = other;
+	}
+}
+
+fn prepend<T>(list: &mut Vec<T>, mut first: Vec<T>) {
+	first.append(list);
+	*list = first;
+}
+
+fn discover(start: &Path) -> Option<PathBuf> {
+	start
+		.ancestors()
+		.map(|dir| dir.join(FILE_NAME))
+		.find(|path| path.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const CONFIG: &str = r#"
+[server]
+port = 8000
+html = { dir = "site" }
+
+[[server.proxy]]
+prefix = "/api"
+target = "http://127.0.0.1:3000"
+
+[[server.throttle]]
+latency_ms = 100
+
+[build]
+app_dir = "frontend"
+
+[profiles.staging.server]
+port = 9000
+
+[[profiles.staging.server.proxy]]
+prefix = "/api"
+target = "http://staging.example.com"
+
+[[profiles.staging.server.throttle]]
+for = "/cui/*"
+latency_ms = 300
+"#;
+
+	/// A project with `cui.toml` at its root and a nested directory to start from.
+	fn project(name: &str) -> PathBuf {
+		let root = env::temp_dir().join(format!("cui-config-{}-{}", name, std::process::id()));
+		fs::create_dir_all(root.join("frontend/src")).unwrap();
+		fs::write(root.join(FILE_NAME), CONFIG).unwrap();
+		root
+	}
+
+	#[test]
+	fn finds_the_config_upward() {
+		let root = project("discover");
+		let config = Config::load_in(&root.join("frontend/src"), None, None).unwrap();
+		assert_eq!(config.path, Some(root.join(FILE_NAME)));
+		assert_eq!(config.root, root);
+		assert_eq!(config.server.port, Some(8000));
+		fs::remove_dir_all(root).unwrap();
+	}
+
+	#[test]
+	fn profile_settings_win() {
+		let root = project("profile");
+		let config = Config::load_in(&root, None, Some("staging")).unwrap();
+		let settings = config.server_settings();
+		assert_eq!(settings.port, 9000);
+		assert_eq!(settings.proxies[0].target, "http://staging.example.com");
+		assert_eq!(settings.proxies.len(), 2);
+		assert_eq!(settings.throttle[0].latency_ms, 300);
+		assert!(Config::load_in(&root, None, Some("missing")).is_err());
+		fs::remove_dir_all(root).unwrap();
+	}
+
+	#[test]
+	fn relative_paths_are_relative_to_the_config() {
+		let root = project("paths");
+		let config = Config::load_in(Path::new("/"), Some(&root.join(FILE_NAME)), None).unwrap();
+		assert_eq!(config.server_settings().html.dir, root.join("site"));
+		assert_eq!(config.server_settings().pkg.dir, root.join("app/pkg"));
+		assert_eq!(config.server_settings().mocks_dir, Some(root.join(mocks::DIR)));
+		assert_eq!(config.build_settings().app_dir, root.join("frontend"));
+		assert_eq!(config.watch_settings().paths, vec![root.join("frontend")]);
+		fs::remove_dir_all(root).unwrap();
+	}
+}
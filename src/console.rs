@@ -0,0 +1,63 @@
+use crate::access;
+use actix_web::{web, HttpRequest, HttpResponse};
+use log::Level;
+use serde::Deserialize;
+
+pub const PATH: &str = "/__cui/console";
+/// The log target browser output is printed under, so `RUST_LOG=browser=warn` can filter it.
+const TARGET: &str = "browser";
+
+/// Console output the client script collected in one tab.
+#[derive(Deserialize)]
+struct Batch {
+	tab: String,
+	entries: Vec<Entry>,
+}
+
+#[derive(Deserialize)]
+struct Entry {
+	level: String,
+	message: String,
+}
+
+/// Prints what the browser logged, panics and uncaught errors included, in the terminal.
+pub async fn endpoint(req: HttpRequest, body: web::Bytes) -> HttpResponse {
+	if !access::same_origin(&req) {
+		return HttpResponse::Forbidden().finish();
+	}
+	// `sendBeacon` can't set a JSON content type, so the body is parsed by hand
+	let batch: Batch = match serde_json::from_slice(&body) {
+		Ok(batch) => batch,
+		Err(_) => return HttpResponse::BadRequest().finish(),
+	};
+	let tab: String = batch.tab.chars().filter(char::is_ascii_alphanumeric).take(8).collect();
+	for entry in batch.entries {
+		let level = match entry.level.as_str() {
+			"error" => Level::Error,
+			"warn" => Level::Warn,
+			"debug" => Level::Debug,
+			_ => Level::Info,
+		};
+		log::log!(target: TARGET, level, "[tab {}] {}", tab, printable(&entry.message));
+	}
+	HttpResponse::NoContent().finish()
+}
+
+/// `message` without the control characters that could rewrite the terminal, line breaks and tabs aside.
+fn printable(message: &str) -> String {
+	message
+		.chars()
+		.filter(|&c| !c.is_control() || c == '\n' || c == '\t')
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn control_characters_are_dropped() {
+		assert_eq!(printable("a\x1b[2Jb\rc\u{7}"), "a[2Jbc");
+		assert_eq!(printable("line\n\tindented"), "line\n\tindented");
+	}
+}
@@ -0,0 +1,212 @@
+use crate::{
+	events::{Event, Events},
+	server::{Listing, Mount, Settings},
+};
+use actix_web::{
+	body::MessageBody,
+	dev::{ServiceRequest, ServiceResponse},
+	middleware::Next,
+	web, HttpRequest, HttpResponse,
+};
+use serde::Serialize;
+use serde_json::json;
+use std::{
+	collections::VecDeque,
+	fs,
+	path::{Path, PathBuf},
+	sync::{
+		atomic::{AtomicU64, Ordering},
+		Mutex,
+	},
+	time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+pub const PATH: &str = "/__cui";
+pub const STATUS_PATH: &str = "/__cui/status";
+const PAGE: &str = include_str!("client/dashboard.html");
+const MAX_REQUESTS: usize = 200;
+const MAX_FILES: usize = 1000;
+
+/// The requests and clients the dashboard shows, builds are kept by [`Events`].
+#[derive(Default)]
+pub struct State {
+	requests: Mutex<VecDeque<Request>>,
+	clients: Mutex<Vec<Client>>,
+	next_client: AtomicU64,
+}
+
+#[derive(Clone, Serialize)]
+struct Request {
+	time: u128,
+	method: String,
+	path: String,
+	status: u16,
+	duration_ms: u128,
+}
+
+#[derive(Clone, Serialize)]
+struct Client {
+	id: u64,
+	address: Option<String>,
+	user_agent: Option<String>,
+	connected_at: u128,
+}
+
+#[derive(Serialize)]
+struct File {
+	url: String,
+	size: u64,
+	modified: Option<u128>,
+}
+
+/// Removes a live reload client from the dashboard when its socket closes.
+pub struct ClientGuard {
+	state: web::Data<State>,
+	id: u64,
+}
+
+impl Drop for ClientGuard {
+	fn drop(&mut self) {
+		self.state.clients.lock().unwrap().retain(|client| client.id != self.id);
+	}
+}
+
+/// Lists a live reload client until the returned guard is dropped.
+pub fn connect(state: &web::Data<State>, req: &HttpRequest) -> ClientGuard {
+	let id = state.next_client.fetch_add(1, Ordering::Relaxed);
+	state.clients.lock().unwrap().push(Client {
+		id,
+		address: req.peer_addr().map(|address| address.ip().to_string()),
+		user_agent: req
+			.headers()
+			.get("user-agent")
+			.and_then(|value| value.to_str().ok())
+			.map(String::from),
+		connected_at: now(),
+	});
+	ClientGuard { state: state.clone(), id }
+}
+
+pub async fn page() -> HttpResponse {
+	HttpResponse::Ok()
+		.content_type("text/html; charset=utf-8")
+		.body(PAGE)
+}
+
+pub async fn status(
+	req: HttpRequest,
+	state: web::Data<State>,
+	events: web::Data<Events>,
+	settings: web::Data<Settings>,
+) -> HttpResponse {
+	let last_build = events.last_build();
+	// while building, the diagnostics of the build before are still worth seeing
+	let (build, diagnostics) = match &last_build {
+		Some(Event::Built { diagnostics, .. }) => ("succeeded", diagnostics.as_slice()),
+		Some(Event::BuildFailed { diagnostics, .. }) => ("failed", diagnostics.as_slice()),
+		_ => ("none", &[][..]),
+	};
+
+	// other machines only get to see the files a listing would show them anyway
+	let local = req.peer_addr().is_none_or(|peer| peer.ip().is_loopback());
+	let mounts: Vec<Mount> = [&settings.pkg, &settings.html]
+		.iter()
+		.filter(|mount| local || mount.listing != Listing::Off)
+		.map(|mount| (*mount).clone())
+		.collect();
+	let roots = vec![settings.pkg.dir.clone(), settings.html.dir.clone()];
+	let files = web::block(move || files(&mounts, &roots)).await.unwrap_or_default();
+
+	HttpResponse::Ok().json(json!({
+		"build": if events.is_building() { "building" } else { build },
+		"live_reload": settings.live_reload,
+		"builds": events.builds(),
+		"diagnostics": diagnostics,
+		"clients": &*state.clients.lock().unwrap(),
+		"requests": &*state.requests.lock().unwrap(),
+		"files": files,
+	}))
+}
+
+/// Keeps the most recent requests for the dashboard, leaving out its own polling.
+pub async fn middleware(
+	req: ServiceRequest,
+	next: Next<impl MessageBody>,
+) -> actix_web::Result<ServiceResponse<impl MessageBody>> {
+	let state = req.app_data::<web::Data<State>>().cloned();
+	let method = req.method().to_string();
+	let path = req.path().to_string();
+	let started = Instant::now();
+	let res = next.call(req).await?;
+
+	if let Some(state) = state.filter(|_| path != STATUS_PATH) {
+		let request = Request {
+			time: now(),
+			method,
+			path,
+			status: res.status().as_u16(),
+			duration_ms: started.elapsed().as_millis(),
+		};
+		push(&mut state.requests.lock().unwrap(), request, MAX_REQUESTS);
+	}
+	Ok(res)
+}
+
+fn push<T>(items: &mut VecDeque<T>, item: T, max: usize) {
+	if items.len() == max {
+		items.pop_front();
+	}
+	items.push_back(item);
+}
+
+/// The files of `mounts`, each under the mount that serves it, when one mount's directory is inside another's.
+fn files(mounts: &[Mount], roots: &[PathBuf]) -> Vec<File> {
+	let roots: Vec<PathBuf> = roots.iter().filter_map(|root| root.canonicalize().ok()).collect();
+	let mut files = Vec::new();
+	for mount in mounts {
+		walk(mount, &mount.dir, &roots, &mut files);
+	}
+	files.sort_by(|a, b| a.url.cmp(&b.url));
+	files
+}
+
+fn walk(mount: &Mount, dir: &Path, roots: &[PathBuf], files: &mut Vec<File>) {
+	let entries = match fs::read_dir(dir) {
+		Ok(entries) => entries,
+		Err(_) => return,
+	};
+	for entry in entries.flatten() {
+		if files.len() >= MAX_FILES {
+			return;
+		}
+		let path = entry.path();
+		let metadata = match entry.metadata() {
+			Ok(metadata) => metadata,
+			Err(_) => continue,
+		};
+		if metadata.is_dir() {
+			if !path.canonicalize().is_ok_and(|path| roots.contains(&path)) {
+				walk(mount, &path, roots, files);
+			}
+			continue;
+		}
+		let relative = path.strip_prefix(&mount.dir).unwrap_or(&path);
+		files.push(File {
+			url: format!(
+				"{}/{}",
+				mount.prefix.trim_end_matches('/'),
+				relative.to_string_lossy().replace('\\', "/")
+			),
+			size: metadata.len(),
+			modified: metadata.modified().ok().map(millis),
+		});
+	}
+}
+
+fn now() -> u128 {
+	millis(SystemTime::now())
+}
+
+fn millis(time: SystemTime) -> u128 {
+	time.duration_since(UNIX_EPOCH).map_or(0, |since| since.as_millis())
+}
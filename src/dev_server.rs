@@ -0,0 +1,484 @@
+pub use crate::{
+	wasm_pack::{BuildSettings, Profile},
+	watch::WatchSettings,
+};
+use crate::{
+	cache::CachePolicy,
+	config::Config,
+	error::{Error, Result},
+	events::{Event, Events},
+	server::{self, Mount, Settings},
+	watch::{self, Watcher},
+};
+use actix_web::{
+	body::{BoxBody, MessageBody},
+	dev::{ServerHandle, ServiceRequest, ServiceResponse},
+	middleware, rt,
+	rt::task::JoinHandle,
+	web,
+};
+use futures_util::future::LocalBoxFuture;
+use std::{
+	fs,
+	future::Future,
+	io,
+	net::SocketAddr,
+	path::PathBuf,
+	sync::Arc,
+};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+/// Middleware added with [`DevServer::middleware`].
+pub type Middleware =
+	Arc<dyn Fn(ServiceRequest, Next) -> LocalBoxFuture<'static, actix_web::Result<ServiceResponse<BoxBody>>> + Send + Sync>;
+type Hook = Box<dyn Fn(&Event) + Send>;
+type Inner = Box<dyn FnOnce(ServiceRequest) -> LocalBoxFuture<'static, actix_web::Result<ServiceResponse<BoxBody>>>>;
+
+/// The dev server of `cui-tools dev`, for running inside other programs and tests.
+///
+/// ```no_run
+/// # async fn example() -> cui_tools::error::Result<()> {
+/// let server = cui_tools::DevServer::new()
+///     .port(0)
+///     .html("/", "app/target/html")
+///     .on_event(|event| println!("{:?}", event))
+///     .start()
+///     .await?;
+/// println!("serving at {}", server.url());
+/// server.shutdown().await
+/// # }
+/// ```
+pub struct DevServer {
+	settings: Settings,
+	build: Option<BuildSettings>,
+	watch: WatchSettings,
+	middleware: Vec<Middleware>,
+	hooks: Vec<Hook>,
+}
+
+impl Default for DevServer {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl DevServer {
+	/// Serves the default app directories with live reload, without building anything.
+	pub fn new() -> Self {
+		Self::with_settings(Settings::default())
+	}
+
+	/// Builds, serves and watches the app the way `cui.toml` describes.
+	pub fn from_config(config: &Config) -> Self {
+		Self::with_settings(config.server_settings())
+			.build(config.build_settings())
+			.watch(config.watch_settings())
+	}
+
+	/// Serves with these settings, with live reload on and unset cache policies set to `no-store`.
+	pub fn with_settings(mut settings: Settings) -> Self {
+		settings.live_reload = true;
+		// a rebuilt bundle has the same file names, so nothing may be reused from before
+		for mount in [&mut settings.pkg, &mut settings.html] {
+			mount.cache.get_or_insert(CachePolicy::NoStore);
+		}
+		Self {
+			settings,
+			build: None,
+			watch: WatchSettings {
+				enabled: false,
+				..WatchSettings::default()
+			},
+			middleware: Vec::new(),
+			hooks: Vec::new(),
+		}
+	}
+
+	pub fn host(mut self, host: impl Into<String>) -> Self {
+		self.settings.host = host.into();
+		self
+	}
+
+	/// Port to listen on, 0 picks a free one.
+	pub fn port(mut self, port: u16) -> Self {
+		self.settings.port = port;
+		self
+	}
+
+	/// Where the wasm-pack output is served from.
+	pub fn pkg(mut self, prefix: &str, dir: impl Into<PathBuf>) -> Self {
+		self.settings.pkg = mount(&self.settings.pkg, prefix, dir);
+		self
+	}
+
+	/// Where the app's pages are served from.
+	pub fn html(mut self, prefix: &str, dir: impl Into<PathBuf>) -> Self {
+		self.settings.html = mount(&self.settings.html, prefix, dir);
+		self
+	}
+
+	pub fn live_reload(mut self, live_reload: bool) -> Self {
+		self.settings.live_reload = live_reload;
+		self
+	}
+
+	/// Builds the app with wasm-pack before serving it.
+	pub fn build(mut self, build: BuildSettings) -> Self {
+		self.build = Some(build);
+		self
+	}
+
+	/// Rebuilds the app when these paths change, when it is built at all.
+	pub fn watch(mut self, watch: WatchSettings) -> Self {
+		self.watch = watch;
+		self
+	}
+
+	/// Runs `middleware` for every request the app gets, after the tools' own access checks
+	/// and before mocks, redirects and files. Middleware runs in the order it is added.
+	pub fn middleware<F, Fut>(mut self, middleware: F) -> Self
+	where
+		F: Fn(ServiceRequest, Next) -> Fut + Send + Sync + 'static,
+		Fut: Future<Output = actix_web::Result<ServiceResponse<BoxBody>>> + 'static,
+	{
+		self.middleware.push(Arc::new(move |req, next| Box::pin(middleware(req, next))));
+		self
+	}
+
+	/// Calls `hook` with every build event, the same ones that make open tabs reload.
+	pub fn on_event(mut self, hook: impl Fn(&Event) + Send + 'static) -> Self {
+		self.hooks.push(Box::new(hook));
+		self
+	}
+
+	/// Builds the app if asked to, then starts serving it. Has to be called on an actix runtime.
+	pub async fn start(self) -> Result<DevServerHandle> {
+		let (events, watcher) = prepare(self.hooks, self.build, &self.watch, &self.settings).await?;
+		let https = self.settings.https;
+		let (server, addresses) = server::bind(self.settings, events.clone(), self.middleware)?;
+		Ok(DevServerHandle {
+			server: server.handle(),
+			task: Some(rt::spawn(server)),
+			events,
+			addresses,
+			https,
+			watcher,
+		})
+	}
+
+	/// Builds the app if asked to, for serving it from an actix app of your own with
+	/// [`DevService::configure`] instead of on its own address. Has to be called on an actix runtime.
+	pub async fn service(self) -> Result<DevService> {
+		let (events, watcher) = prepare(self.hooks, self.build, &self.watch, &self.settings).await?;
+		Ok(DevService {
+			services: server::Services::new(self.settings, events.clone(), self.middleware),
+			events,
+			watcher: watcher.map(Arc::new),
+		})
+	}
+}
+
+fn mount(mount: &Mount, prefix: &str, dir: impl Into<PathBuf>) -> Mount {
+	Mount {
+		listing: mount.listing,
+		cache: mount.cache,
+		..Mount::new(prefix, dir)
+	}
+}
+
+/// Hooks the event handlers up, then builds and starts watching when there is a build.
+async fn prepare(
+	hooks: Vec<Hook>,
+	build: Option<BuildSettings>,
+	watch: &WatchSettings,
+	settings: &Settings,
+) -> Result<(Events, Option<Watcher>)> {
+	let events = Events::new();
+	for hook in hooks {
+		let mut receiver = events.subscribe();
+		rt::spawn(async move {
+			loop {
+				match receiver.recv().await {
+					Ok(event) => hook(&event),
+					Err(RecvError::Lagged(_)) => {}
+					Err(RecvError::Closed) => break,
+				}
+			}
+		});
+	}
+
+	let watcher = match build {
+		Some(build) => {
+			first_build(&build, watch, settings, &events).await?;
+			Some(watch::spawn(watch, build, events.clone())?)
+		}
+		None => None,
+	};
+	Ok((events, watcher))
+}
+
+async fn first_build(build: &BuildSettings, watch: &WatchSettings, settings: &Settings, events: &Events) -> Result<()> {
+	match watch::build(build, events).await {
+		Ok(_) => {
+			for mount in &[&settings.pkg, &settings.html] {
+				if !mount.dir.is_dir() {
+					return Err(Error::build(format!(
+						"the build finished but {} does not exist",
+						mount.dir.display()
+					)));
+				}
+			}
+			Ok(())
+		}
+		// with a watcher the next save can fix the build, so serve the errors until then
+		Err(Error::Build { .. }) if watch.enabled => {
+			log::warn!("serving an error page until the app builds");
+			// the mounts are resolved once at startup, so they have to exist before the first good build
+			for mount in &[&settings.pkg, &settings.html] {
+				fs::create_dir_all(&mount.dir)?;
+			}
+			Ok(())
+		}
+		Err(error) => {
+			if let Error::Build { .. } = error {
+				log::error!("not starting the server because the app failed to build");
+			}
+			Err(error)
+		}
+	}
+}
+
+/// A running [`DevServer`].
+pub struct DevServerHandle {
+	server: ServerHandle,
+	task: Option<JoinHandle<io::Result<()>>>,
+	events: Events,
+	addresses: Vec<SocketAddr>,
+	https: bool,
+	watcher: Option<Watcher>,
+}
+
+impl DevServerHandle {
+	/// The addresses the server listens on.
+	pub fn addresses(&self) -> &[SocketAddr] {
+		&self.addresses
+	}
+
+	/// The address of the app, for the first address the server listens on.
+	pub fn url(&self) -> String {
+		let scheme = if self.https { "https" } else { "http" };
+		match self.addresses.first() {
+			Some(address) => format!("{}://{}", scheme, address),
+			None => format!("{}://localhost", scheme),
+		}
+	}
+
+	/// Receives the build events from now on.
+	pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+		self.events.subscribe()
+	}
+
+	/// The outcome of the most recent build.
+	pub fn last_build(&self) -> Option<Event> {
+		self.events.last_build()
+	}
+
+	/// Starts a build as if a source had changed. Does nothing when the app isn't built.
+	pub fn rebuild(&self) {
+		if let Some(watcher) = &self.watcher {
+			watcher.rebuild();
+		}
+	}
+
+	/// Waits until the server stops, on Ctrl-C or a call to [`shutdown`](Self::shutdown).
+	pub async fn wait(&mut self) -> Result<()> {
+		match self.task.as_mut() {
+			Some(task) => {
+				let result = task.await;
+				self.task = None;
+				match result {
+					Ok(result) => Ok(result?),
+					Err(error) => Err(Error::Other(format!("the server stopped unexpectedly: {}", error))),
+				}
+			}
+			None => Ok(()),
+		}
+	}
+
+	/// Stops the server after the requests in flight are answered, and stops watching.
+	pub async fn shutdown(mut self) -> Result<()> {
+		self.server.stop(true).await;
+		self.wait().await
+	}
+}
+
+impl Drop for DevServerHandle {
+	fn drop(&mut self) {
+		// stopping asks the server right away, waiting for it is up to `shutdown`
+		drop(self.server.stop(false));
+	}
+}
+
+/// A [`DevServer`] to serve from an actix app of your own. Clones share the build and its events,
+/// and watching stops when the last one is dropped.
+///
+/// ```no_run
+/// # async fn example() -> cui_tools::error::Result<()> {
+/// use actix_web::{web, App, HttpServer};
+///
+/// let dev = cui_tools::DevServer::new().html("/", "app/target/html").service().await?;
+/// HttpServer::new(move || {
+///     App::new()
+///         // the dev server takes every path it gets, so it goes last
+///         .route("/api/health", web::get().to(|| async { "ok" }))
+///         .configure(dev.configure())
+/// })
+/// .bind(("127.0.0.1", 8080))?
+/// .run()
+/// .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct DevService {
+	services: server::Services,
+	events: Events,
+	watcher: Option<Arc<Watcher>>,
+}
+
+impl DevService {
+	/// Registers the mounts, the `/__cui` endpoints and the dev server's middleware in an app,
+	/// after the routes registered before it. Routes registered after it are never reached.
+	pub fn configure(&self) -> impl FnOnce(&mut web::ServiceConfig) + 'static {
+		let services = self.services.clone();
+		move |config| services.configure(config)
+	}
+
+	/// Receives the build events from now on.
+	pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+		self.events.subscribe()
+	}
+
+	/// The outcome of the most recent build.
+	pub fn last_build(&self) -> Option<Event> {
+		self.events.last_build()
+	}
+
+	/// Starts a build as if a source had changed. Does nothing when the app isn't built.
+	pub fn rebuild(&self) {
+		if let Some(watcher) = &self.watcher {
+			watcher.rebuild();
+		}
+	}
+}
+
+/// The rest of the middleware chain, with the dev server behind it.
+pub struct Next {
+	chain: web::Data<Vec<Middleware>>,
+	index: usize,
+	inner: Inner,
+}
+
+impl Next {
+	pub async fn call(self, req: ServiceRequest) -> actix_web::Result<ServiceResponse<BoxBody>> {
+		match self.chain.get(self.index).cloned() {
+			Some(middleware) => {
+				let next = Next {
+					index: self.index + 1,
+					..self
+				};
+				middleware(req, next).await
+			}
+			None => (self.inner)(req).await,
+		}
+	}
+}
+
+/// Runs the middleware added with [`DevServer::middleware`].
+pub(crate) async fn middleware(
+	req: ServiceRequest,
+	next: middleware::Next<impl MessageBody + 'static>,
+) -> actix_web::Result<ServiceResponse<BoxBody>> {
+	let chain = match req.app_data::<web::Data<Vec<Middleware>>>() {
+		Some(chain) if !chain.is_empty() => chain.clone(),
+		_ => return next.call(req).await.map(ServiceResponse::map_into_boxed_body),
+	};
+	let inner: Inner = Box::new(move |req| {
+		Box::pin(async move { next.call(req).await.map(ServiceResponse::map_into_boxed_body) })
+	});
+	Next { chain, index: 0, inner }.call(req).await
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::inject;
+
+	#[actix_web::test]
+	async fn serves_files_until_shut_down() {
+		let dir = std::env::temp_dir().join(format!("cui-dev-server-{}", std::process::id()));
+		fs::create_dir_all(dir.join("pkg")).unwrap();
+		fs::create_dir_all(dir.join("html")).unwrap();
+		fs::write(dir.join("html/hello.txt"), "hello").unwrap();
+
+		let server = DevServer::new()
+			.port(0)
+			.pkg("/cui", dir.join("pkg"))
+			.html("/", dir.join("html"))
+			.start()
+			.await
+			.unwrap();
+		assert_ne!(server.addresses()[0].port(), 0);
+
+		let url = format!("{}/hello.txt", server.url());
+		let mut res = awc::Client::new().get(&url).send().await.unwrap();
+		assert_eq!(res.status(), 200);
+		assert_eq!(res.body().await.unwrap(), "hello");
+
+		server.shutdown().await.unwrap();
+		assert!(awc::Client::new().get(&url).send().await.is_err());
+		fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[actix_web::test]
+	async fn serves_files_from_an_app_of_its_own() {
+		let dir = std::env::temp_dir().join(format!("cui-dev-service-{}", std::process::id()));
+		fs::create_dir_all(dir.join("pkg")).unwrap();
+		fs::create_dir_all(dir.join("html")).unwrap();
+		fs::write(dir.join("html/hello.txt"), "hello").unwrap();
+
+		let dev = DevServer::new()
+			.pkg("/cui", dir.join("pkg"))
+			.html("/", dir.join("html"))
+			.service()
+			.await
+			.unwrap();
+		let server = actix_web::HttpServer::new(move || {
+			actix_web::App::new()
+				.route("/own", web::get().to(|| async { "own route" }))
+				.configure(dev.configure())
+		})
+		.workers(1)
+		.bind(("127.0.0.1", 0))
+		.unwrap();
+		let url = format!("http://{}", server.addrs()[0]);
+		let server = server.run();
+		let handle = server.handle();
+		rt::spawn(server);
+
+		let client = awc::Client::new();
+		let mut res = client.get(format!("{}/hello.txt", url)).send().await.unwrap();
+		assert_eq!(res.status(), 200);
+		assert_eq!(res.body().await.unwrap(), "hello");
+		let mut res = client.get(format!("{}/own", url)).send().await.unwrap();
+		assert_eq!(res.body().await.unwrap(), "own route");
+		let res = client.get(format!("{}/__cui/status", url)).send().await.unwrap();
+		assert_eq!(res.status(), 200);
+		assert_eq!(res.headers().get("cache-control").unwrap(), "no-store");
+		let mut res = client.get(format!("{}/", url)).send().await.unwrap();
+		assert!(String::from_utf8_lossy(&res.body().await.unwrap()).contains(inject::CLIENT_PATH));
+
+		handle.stop(true).await;
+		fs::remove_dir_all(dir).unwrap();
+	}
+}
@@ -0,0 +1,180 @@
+use crate::events::{Event, Events};
+use actix_web::{web, HttpResponse};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+pub const PATH: &str = "/__cui/diagnostics";
+
+/// One message reported by the compiler or wasm-pack.
+#[derive(Clone, Debug, Serialize)]
+pub struct Diagnostic {
+	pub severity: Severity,
+	pub code: Option<String>,
+	pub message: String,
+	pub spans: Vec<Span>,
+	/// The diagnostic as the compiler would have printed it, including code snippets.
+	pub rendered: String,
+	pub suggestions: Vec<Suggestion>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+	Error,
+	Warning,
+	Note,
+	Help,
+	#[serde(rename = "failure-note")]
+	FailureNote,
+	#[serde(other)]
+	Other,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Span {
+	pub file: String,
+	pub line_start: usize,
+	pub line_end: usize,
+	pub column_start: usize,
+	pub column_end: usize,
+	pub is_primary: bool,
+	pub label: Option<String>,
+}
+
+/// A fix the compiler proposes, replacing a span with new text.
+#[derive(Clone, Debug, Serialize)]
+pub struct Suggestion {
+	pub message: String,
+	pub span: Span,
+	pub replacement: String,
+}
+
+/// A line of `cargo build --message-format=json` output.
+#[derive(Deserialize)]
+struct CargoMessage {
+	reason: String,
+	message: Option<CompilerMessage>,
+}
+
+#[derive(Deserialize)]
+struct CompilerMessage {
+	message: String,
+	code: Option<CompilerCode>,
+	level: Severity,
+	spans: Vec<CompilerSpan>,
+	children: Vec<CompilerMessage>,
+	rendered: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CompilerCode {
+	code: String,
+}
+
+#[derive(Deserialize)]
+struct CompilerSpan {
+	file_name: String,
+	line_start: usize,
+	line_end: usize,
+	column_start: usize,
+	column_end: usize,
+	is_primary: bool,
+	label: Option<String>,
+	suggested_replacement: Option<String>,
+}
+
+impl Diagnostic {
+	pub fn is_error(&self) -> bool {
+		self.severity == Severity::Error
+	}
+
+	pub fn primary_span(&self) -> Option<&Span> {
+		self.spans
+			.iter()
+			.find(|span| span.is_primary)
+			.or_else(|| self.spans.first())
+	}
+
+	/// Reads one line of cargo's JSON output, anything but a compiler message is `None`.
+	pub fn from_json(line: &str) -> Option<Self> {
+		let message: CargoMessage = serde_json::from_str(line).ok()?;
+		if message.reason != "compiler-message" {
+			return None;
+		}
+		let message = message.message?;
+		// the summary lines ("aborting due to ...") carry no information of their own
+		if message.level == Severity::FailureNote
+			|| (message.spans.is_empty() && message.message.starts_with("aborting due to"))
+		{
+			return None;
+		}
+
+		let suggestions = message
+			.children
+			.iter()
+			.flat_map(|child| {
+				child.spans.iter().filter_map(move |span| {
+					Some(Suggestion {
+						message: child.message.clone(),
+						replacement: span.suggested_replacement.clone()?,
+						span: span.into(),
+					})
+				})
+			})
+			.collect();
+
+		let rendered = match message.rendered {
+			Some(rendered) => rendered,
+			None => message.message.clone(),
+		};
+		Some(Self {
+			severity: message.level,
+			code: message.code.map(|code| code.code),
+			rendered,
+			spans: message.spans.iter().map(Span::from).collect(),
+			message: message.message,
+			suggestions,
+		})
+	}
+
+	/// Reads the `Error: ...` lines wasm-pack prints about failures of its own.
+	pub fn from_text(line: &str) -> Option<Self> {
+		let message = line.strip_prefix("Error:")?.trim();
+		Some(Self {
+			severity: Severity::Error,
+			code: None,
+			message: message.to_string(),
+			spans: Vec::new(),
+			rendered: line.to_string(),
+			suggestions: Vec::new(),
+		})
+	}
+}
+
+impl From<&CompilerSpan> for Span {
+	fn from(span: &CompilerSpan) -> Self {
+		Self {
+			file: span.file_name.clone(),
+			line_start: span.line_start,
+			line_end: span.line_end,
+			column_start: span.column_start,
+			column_end: span.column_end,
+			is_primary: span.is_primary,
+			label: span.label.clone(),
+		}
+	}
+}
+
+/// Serves the diagnostics of the most recent build.
+pub async fn endpoint(events: web::Data<Events>) -> HttpResponse {
+	let body = match events.last_build() {
+		Some(Event::Built { diagnostics, .. }) => json!({ "status": "succeeded", "diagnostics": diagnostics }),
+		Some(Event::BuildFailed { message, diagnostics, .. }) => json!({
+			"status": "failed",
+			"message": message,
+			"diagnostics": diagnostics,
+		}),
+		_ => json!({ "status": "pending", "diagnostics": [] }),
+	};
+	HttpResponse::Ok().json(body)
+}
@@ -0,0 +1,420 @@
+use crate::{
+	compress::{self, Sizes},
+	error::{Error, Result},
+	server::Settings,
+};
+use sha2::{Digest, Sha256};
+use std::{
+	cmp::Reverse,
+	fs, io,
+	path::{Path, PathBuf},
+};
+
+/// Extensions of files that get a content hash in their name.
+const HASHED: &[&str] = &[
+	"wasm", "js", "mjs", "css", "png", "jpg", "jpeg", "gif", "svg", "webp", "woff", "woff2",
+];
+/// Extensions of hashed files that can refer to other hashed files.
+const TEXT: &[&str] = &["js", "mjs", "css"];
+/// wasm-pack output that is only useful for publishing to npm.
+const SKIPPED: &[&str] = &["package.json", "README.md", ".gitignore"];
+const HASH_LEN: usize = 10;
+const TRANSFERRED: &str = "transferred at best";
+
+pub struct File {
+	pub path: PathBuf,
+	pub size: u64,
+	/// Sizes of the `.gz` and `.br` siblings, for file types that get them.
+	pub compressed: Option<Sizes>,
+}
+
+/// A file given a content-hashed name, by its path in `dist` before and after.
+struct Rename {
+	from: PathBuf,
+	to: PathBuf,
+}
+
+/// Refuses a `dist` that [`assemble`] would delete something else along with: one of the
+/// `protected` directories or a directory it copies the mounts from, or that is inside one of those.
+pub fn check_dir(dist: &Path, settings: &Settings, protected: &[&Path]) -> Result<()> {
+	let target = absolute(dist);
+	for dir in protected {
+		if absolute(dir) == target {
+			return Err(refused(dist, "would delete", dir));
+		}
+	}
+	for mount in &[&settings.pkg, &settings.html] {
+		let source = absolute(&mount.dir);
+		if source.starts_with(&target) {
+			return Err(refused(dist, "would delete", &mount.dir));
+		}
+		// copying a mount into a directory inside it would copy the copy again
+		if target.starts_with(&source) {
+			return Err(refused(dist, "would be copied into itself from", &mount.dir));
+		}
+	}
+	Ok(())
+}
+
+fn refused(dist: &Path, problem: &str, dir: &Path) -> Error {
+	let dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+	Error::Usage(format!(
+		"{} cannot hold the release build, it {} {}; pick another `dist_dir`",
+		dist.display(),
+		problem,
+		dir.display()
+	))
+}
+
+/// `path` made absolute with symlinks resolved, as far as it exists.
+fn absolute(path: &Path) -> PathBuf {
+	let mut existing = if path.as_os_str().is_empty() { Path::new(".") } else { path };
+	let mut missing = Vec::new();
+	loop {
+		if let Ok(resolved) = existing.canonicalize() {
+			return missing.iter().rev().fold(resolved, |path, name| path.join(name));
+		}
+		match (existing.parent(), existing.file_name()) {
+			(Some(parent), Some(name)) => {
+				missing.push(name.to_owned());
+				existing = if parent.as_os_str().is_empty() { Path::new(".") } else { parent };
+			}
+			_ => return path.to_path_buf(),
+		}
+	}
+}
+
+/// Copies both mounts into `dist`, at their URL prefixes, and gives assets content-hashed names.
+pub fn assemble(settings: &Settings, dist: &Path) -> Result<Vec<File>> {
+	if dist.exists() {
+		fs::remove_dir_all(dist)?;
+	}
+	for mount in &[&settings.html, &settings.pkg] {
+		copy_dir(&mount.dir, &dist.join(mount.prefix.trim_start_matches('/')))?;
+	}
+
+	let mut files = Vec::new();
+	walk(dist, &mut files)?;
+
+	let (hashed, others): (Vec<_>, Vec<_>) = files.into_iter().partition(|path| has_extension(path, HASHED));
+	let (mut text, binary): (Vec<_>, Vec<_>) = hashed.into_iter().partition(|path| has_extension(path, TEXT));
+
+	let mut renames = Vec::new();
+	for path in binary {
+		let content = fs::read(&path)?;
+		renames.push(rename(path, &content)?);
+	}
+
+	// hash files before the files that refer to them, so references pick up the final names
+	while !text.is_empty() {
+		let contents = text
+			.iter()
+			.map(fs::read_to_string)
+			.collect::<io::Result<Vec<_>>>()?;
+		let index = (0..text.len())
+			.find(|&index| {
+				text.iter()
+					.enumerate()
+					.all(|(other, path)| other == index || !refers_to(&contents[index], &references(dist, &text[index], path)))
+			})
+			.unwrap_or(0);
+
+		let path = text.remove(index);
+		let content = rewrite(&contents[index], &replacements(dist, &path, &renames));
+		renames.push(rename(path, content.as_bytes())?);
+	}
+
+	for path in others.iter().filter(|path| has_extension(path, &["html", "htm"])) {
+		let content = fs::read_to_string(path)?;
+		fs::write(path, rewrite(&content, &replacements(dist, path, &renames)))?;
+	}
+
+	let mut files = Vec::new();
+	walk(dist, &mut files)?;
+	files.sort();
+	files
+		.into_iter()
+		.map(|path| {
+			let compressed = match compress::is_compressible(&path) {
+				true => Some(compress::write_siblings(&path)?),
+				false => None,
+			};
+			Ok(File {
+				size: fs::metadata(&path)?.len(),
+				compressed,
+				path,
+			})
+		})
+		.collect()
+}
+
+/// Whether a file name carries a content hash added by [`assemble`].
+pub fn is_hashed(name: &str) -> bool {
+	let mut parts = name.rsplit('.');
+	parts.next();
+	parts
+		.next()
+		.is_some_and(|hash| hash.len() == HASH_LEN && hash.bytes().all(|byte| byte.is_ascii_hexdigit()))
+}
+
+pub fn print_summary(dist: &Path, files: &[File]) {
+	let width = files
+		.iter()
+		.map(|file| relative(dist, &file.path).len())
+		.chain(Some(TRANSFERRED.len()))
+		.max()
+		.unwrap_or(0);
+	let row = |name: &str, size: u64, compressed: Option<&Sizes>| {
+		let (gzip, brotli) = match compressed {
+			Some(sizes) => (
+				format_size(sizes.gzip.unwrap_or(size)),
+				format_size(sizes.brotli.unwrap_or(size)),
+			),
+			None => ("-".into(), "-".into()),
+		};
+		println!("  {:width$}  {:>10}  {:>10}  {:>10}", name, format_size(size), gzip, brotli, width = width);
+	};
+
+	println!("  {:width$}  {:>10}  {:>10}  {:>10}", "file", "size", "gzip", "brotli", width = width);
+	for file in files {
+		row(&relative(dist, &file.path), file.size, file.compressed.as_ref());
+	}
+	let total = |size: fn(&Sizes) -> Option<u64>| -> u64 {
+		files
+			.iter()
+			.map(|file| file.compressed.as_ref().and_then(size).unwrap_or(file.size))
+			.sum()
+	};
+	let totals = Sizes {
+		gzip: Some(total(|sizes| sizes.gzip)),
+		brotli: Some(total(|sizes| sizes.brotli)),
+	};
+	row("total", files.iter().map(|file| file.size).sum(), Some(&totals));
+	// a browser downloads the smallest version of each file it is offered
+	let transferred = total(|sizes| sizes.gzip.into_iter().chain(sizes.brotli).min());
+	println!("  {:width$}  {:>10}", TRANSFERRED, format_size(transferred), width = width);
+}
+
+pub fn format_size(bytes: u64) -> String {
+	match bytes {
+		0..=1023 => format!("{} B", bytes),
+		1024..=1_048_575 => format!("{:.1} KiB", bytes as f64 / 1024.0),
+		_ => format!("{:.1} MiB", bytes as f64 / 1_048_576.0),
+	}
+}
+
+fn relative(root: &Path, path: &Path) -> String {
+	path.strip_prefix(root).unwrap_or(path).display().to_string()
+}
+
+/// Writes `content` under a hashed name and removes the original.
+fn rename(path: PathBuf, content: &[u8]) -> io::Result<Rename> {
+	let hash = Sha256::digest(content)
+		.iter()
+		.map(|byte| format!("{:02x}", byte))
+		.collect::<String>();
+	let name = file_name(&path);
+	let hashed = match name.rsplit_once('.') {
+		Some((stem, extension)) => format!("{}.{}.{}", stem, &hash[..HASH_LEN], extension),
+		None => format!("{}.{}", name, &hash[..HASH_LEN]),
+	};
+	let to = path.with_file_name(&hashed);
+	fs::write(&to, content)?;
+	fs::remove_file(&path)?;
+	Ok(Rename { from: path, to })
+}
+
+/// The ways `referrer` can refer to `target`: relative to its own directory, and from the site root.
+fn references(dist: &Path, referrer: &Path, target: &Path) -> [String; 2] {
+	let from = referrer.parent().unwrap_or(dist);
+	[relative_url(from, target), format!("/{}", relative_url(dist, target))]
+}
+
+/// What `referrer` has to change for `renames`, the longest references first,
+/// so a file named like one in another directory is only matched with its full path.
+fn replacements(dist: &Path, referrer: &Path, renames: &[Rename]) -> Vec<(String, String)> {
+	let mut replacements = Vec::new();
+	for rename in renames {
+		let from = references(dist, referrer, &rename.from);
+		let to = references(dist, referrer, &rename.to);
+		replacements.extend(from.iter().cloned().zip(to.iter().cloned()));
+	}
+	replacements.sort_by_key(|(from, _)| Reverse(from.len()));
+	replacements
+}
+
+/// The URL path from the directory `from` to `to`, with `..` to leave `from` where needed.
+fn relative_url(from: &Path, to: &Path) -> String {
+	let from: Vec<_> = from.components().collect();
+	let to: Vec<_> = to.components().collect();
+	let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+	let mut parts = vec!["..".to_string(); from.len() - common];
+	parts.extend(to[common..].iter().map(|part| part.as_os_str().to_string_lossy().into_owned()));
+	parts.join("/")
+}
+
+/// Replaces references to renamed files, only where the old name is a whole path segment.
+fn rewrite(content: &str, renames: &[(String, String)]) -> String {
+	let mut content = content.to_string();
+	for (from, to) in renames {
+		let mut result = String::with_capacity(content.len());
+		let mut rest = content.as_str();
+		while let Some(index) = find_reference(rest, from) {
+			result.push_str(&rest[..index]);
+			result.push_str(to);
+			rest = &rest[index + from.len()..];
+		}
+		result.push_str(rest);
+		content = result;
+	}
+	content
+}
+
+fn refers_to(content: &str, references: &[String]) -> bool {
+	references.iter().any(|reference| find_reference(content, reference).is_some())
+}
+
+fn find_reference(content: &str, name: &str) -> Option<usize> {
+	let is_boundary = |c: char| !(c.is_alphanumeric() || c == '_' || c == '-' || c == '.');
+	let mut start = 0;
+	while let Some(found) = content[start..].find(name) {
+		let index = start + found;
+		let before = content[..index].chars().next_back();
+		let after = content[index + name.len()..].chars().next();
+		if before.is_none_or(is_boundary) && after.is_none_or(is_boundary) {
+			return Some(index);
+		}
+		start = index + name.len();
+	}
+	None
+}
+
+fn file_name(path: &Path) -> String {
+	path.file_name()
+		.map(|name| name.to_string_lossy().into_owned())
+		.unwrap_or_default()
+}
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+	path.extension()
+		.and_then(|extension| extension.to_str())
+		.is_some_and(|extension| extensions.contains(&extension))
+}
+
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+	fs::create_dir_all(to)?;
+	for entry in fs::read_dir(from)? {
+		let entry = entry?;
+		let name = entry.file_name();
+		if entry.file_type()?.is_dir() {
+			copy_dir(&entry.path(), &to.join(&name))?;
+		} else if !SKIPPED.iter().any(|skipped| name == *skipped) && !name.to_string_lossy().ends_with(".d.ts") {
+			fs::copy(entry.path(), to.join(&name))?;
+		}
+	}
+	Ok(())
+}
+
+fn walk(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+	for entry in fs::read_dir(dir)? {
+		let entry = entry?;
+		if entry.file_type()?.is_dir() {
+			walk(&entry.path(), files)?;
+		} else {
+			files.push(entry.path());
+		}
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn pairs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+		pairs.iter().map(|(from, to)| (from.to_string(), to.to_string())).collect()
+	}
+
+	#[test]
+	fn references_are_whole_path_segments() {
+		assert_eq!(find_reference("import 'app.js'", "app.js"), Some(8));
+		assert_eq!(find_reference("src=\"/pkg/app.js\"", "app.js"), Some(10));
+		assert_eq!(find_reference("my_app.js app.js", "app.js"), Some(10));
+		assert_eq!(find_reference("app.jsx", "app.js"), None);
+		assert_eq!(find_reference("web-app.js", "app.js"), None);
+		assert_eq!(find_reference("app.js.map", "app.js"), None);
+	}
+
+	#[test]
+	fn rewrite_replaces_every_reference() {
+		let renames = pairs(&[("app.js", "app.0123456789.js"), ("app_bg.wasm", "app_bg.abcdef0123.wasm")]);
+		assert_eq!(
+			rewrite("import 'app.js'; fetch('app_bg.wasm'); 'app.js'; 'my_app.js'", &renames),
+			"import 'app.0123456789.js'; fetch('app_bg.abcdef0123.wasm'); 'app.0123456789.js'; 'my_app.js'"
+		);
+	}
+
+	#[test]
+	fn hashed_names() {
+		assert!(is_hashed("app.0123456789.js"));
+		assert!(is_hashed("app_bg.abcdef0123.wasm"));
+		assert!(!is_hashed("app.js"));
+		assert!(!is_hashed("app.0123.js"));
+		assert!(!is_hashed("app.ghijklmnop.js"));
+		assert!(!is_hashed("0123456789"));
+	}
+
+	#[test]
+	fn relative_urls() {
+		assert_eq!(relative_url(Path::new("dist/cui"), Path::new("dist/cui/app.js")), "app.js");
+		assert_eq!(
+			relative_url(Path::new("dist/cui/snippets/a-1"), Path::new("dist/cui/snippets/b-2/inline0.js")),
+			"../b-2/inline0.js"
+		);
+		assert_eq!(relative_url(Path::new("dist"), Path::new("dist/cui/app.js")), "cui/app.js");
+	}
+
+	#[test]
+	fn files_with_the_same_name_are_told_apart_by_their_path() {
+		let dist = Path::new("dist");
+		let renames = [
+			Rename {
+				from: dist.join("cui/snippets/a-1/inline0.js"),
+				to: dist.join("cui/snippets/a-1/inline0.0123456789.js"),
+			},
+			Rename {
+				from: dist.join("cui/snippets/b-2/inline0.js"),
+				to: dist.join("cui/snippets/b-2/inline0.abcdef0123.js"),
+			},
+		];
+		let content = "import './snippets/a-1/inline0.js'; import '/cui/snippets/b-2/inline0.js';";
+		assert_eq!(
+			rewrite(content, &replacements(dist, &dist.join("cui/app.js"), &renames)),
+			"import './snippets/a-1/inline0.0123456789.js'; import '/cui/snippets/b-2/inline0.abcdef0123.js';"
+		);
+		assert_eq!(
+			rewrite("import './inline0.js'", &replacements(dist, &dist.join("cui/snippets/b-2/other.js"), &renames)),
+			"import './inline0.abcdef0123.js'"
+		);
+	}
+
+	#[test]
+	fn refuses_to_empty_the_project_or_its_mounts() {
+		let root = std::env::temp_dir().join(format!("cui-dist-{}", std::process::id()));
+		let mut settings = Settings::default();
+		settings.pkg.dir = root.join("app/pkg");
+		settings.html.dir = root.join("app/target/html");
+		fs::create_dir_all(&settings.html.dir).unwrap();
+		let app = root.join("app");
+		let protected = [root.as_path(), app.as_path()];
+
+		assert!(check_dir(&root.join("dist"), &settings, &protected).is_ok());
+		assert!(check_dir(&root.join("app/target/dist"), &settings, &protected).is_ok());
+		assert!(check_dir(&root, &settings, &protected).is_err());
+		assert!(check_dir(&root.join("app/../app"), &settings, &protected).is_err());
+		assert!(check_dir(&root.join("app/target"), &settings, &protected).is_err());
+		assert!(check_dir(&settings.pkg.dir, &settings, &protected).is_err());
+		assert!(check_dir(&settings.html.dir.join("dist"), &settings, &protected).is_err());
+		fs::remove_dir_all(root).unwrap();
+	}
+}
@@ -0,0 +1,75 @@
+use crate::diagnostics::Diagnostic;
+use std::{fmt, io};
+
+/// Process exit codes shared by every subcommand.
+pub mod exit {
+	pub const FAILURE: i32 = 1;
+	pub const USAGE: i32 = 2;
+	pub const BUILD: i32 = 3;
+	pub const MISSING_TOOL: i32 = 4;
+	pub const CONFIG: i32 = 5;
+}
+
+#[derive(Debug)]
+pub enum Error {
+	Io(io::Error),
+	Other(String),
+	Build {
+		message: String,
+		diagnostics: Vec<Diagnostic>,
+	},
+	MissingTool(String),
+	Usage(String),
+	Config(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+	pub fn build(message: impl Into<String>) -> Self {
+		Error::Build {
+			message: message.into(),
+			diagnostics: Vec::new(),
+		}
+	}
+
+	pub fn exit_code(&self) -> i32 {
+		match self {
+			Error::Io(_) | Error::Other(_) => exit::FAILURE,
+			Error::Build { .. } => exit::BUILD,
+			Error::MissingTool(_) => exit::MISSING_TOOL,
+			Error::Usage(_) => exit::USAGE,
+			Error::Config(_) => exit::CONFIG,
+		}
+	}
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Error::Io(error) => write!(f, "{}", error),
+			Error::Other(message) => write!(f, "{}", message),
+			Error::Build { message, diagnostics } => {
+				write!(f, "build failed: {}", message)?;
+				for diagnostic in diagnostics.iter().filter(|diagnostic| diagnostic.is_error()) {
+					match diagnostic.primary_span() {
+						Some(span) => write!(f, "\n  {}:{}: {}", span.file, span.line_start, diagnostic.message)?,
+						None => write!(f, "\n  {}", diagnostic.message)?,
+					}
+				}
+				Ok(())
+			}
+			Error::MissingTool(tool) => write!(f, "not found: {} (see `cui-tools doctor`)", tool),
+			Error::Usage(message) => write!(f, "{}", message),
+			Error::Config(message) => write!(f, "invalid configuration: {}", message),
+		}
+	}
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+	fn from(error: io::Error) -> Self {
+		Error::Io(error)
+	}
+}
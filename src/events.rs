@@ -0,0 +1,117 @@
+use crate::diagnostics::Diagnostic;
+use serde::Serialize;
+use std::{
+	collections::VecDeque,
+	sync::{Arc, Mutex},
+	time::{SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::broadcast;
+
+const CAPACITY: usize = 256;
+/// How many finished builds are remembered.
+const HISTORY: usize = 50;
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum Event {
+	Building,
+	Built {
+		duration_ms: u128,
+		diagnostics: Vec<Diagnostic>,
+	},
+	BuildFailed {
+		message: String,
+		duration_ms: u128,
+		diagnostics: Vec<Diagnostic>,
+	},
+}
+
+/// A finished build, without its diagnostics.
+#[derive(Clone, Debug, Serialize)]
+pub struct BuildRecord {
+	pub succeeded: bool,
+	/// Milliseconds since the Unix epoch.
+	pub finished_at: u128,
+	pub duration_ms: u128,
+	pub errors: usize,
+	pub warnings: usize,
+}
+
+/// Fan-out channel for everything the dev server reports, every subscriber sees every event.
+#[derive(Clone)]
+pub struct Events {
+	sender: broadcast::Sender<Event>,
+	history: Arc<Mutex<History>>,
+}
+
+#[derive(Default)]
+struct History {
+	building: bool,
+	last_build: Option<Event>,
+	builds: VecDeque<BuildRecord>,
+}
+
+impl Default for Events {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Events {
+	pub fn new() -> Self {
+		let (sender, _) = broadcast::channel(CAPACITY);
+		Self {
+			sender,
+			history: Arc::default(),
+		}
+	}
+
+	pub fn send(&self, event: Event) {
+		{
+			let mut history = self.history.lock().unwrap();
+			let (succeeded, duration_ms, diagnostics) = match &event {
+				Event::Building => (None, 0, &[][..]),
+				Event::Built { duration_ms, diagnostics } => (Some(true), *duration_ms, diagnostics.as_slice()),
+				Event::BuildFailed { duration_ms, diagnostics, .. } => (Some(false), *duration_ms, diagnostics.as_slice()),
+			};
+			history.building = succeeded.is_none();
+			if let Some(succeeded) = succeeded {
+				let errors = diagnostics.iter().filter(|diagnostic| diagnostic.is_error()).count();
+				if history.builds.len() == HISTORY {
+					history.builds.pop_front();
+				}
+				history.builds.push_back(BuildRecord {
+					succeeded,
+					finished_at: SystemTime::now()
+						.duration_since(UNIX_EPOCH)
+						.map_or(0, |since| since.as_millis()),
+					duration_ms,
+					errors,
+					warnings: diagnostics.len() - errors,
+				});
+				history.last_build = Some(event.clone());
+			}
+		}
+		// nobody listening is fine
+		let _ = self.sender.send(event);
+	}
+
+	/// The outcome of the most recent build, so late subscribers can catch up.
+	pub fn last_build(&self) -> Option<Event> {
+		self.history.lock().unwrap().last_build.clone()
+	}
+
+	/// Whether a build has started and not finished yet.
+	pub fn is_building(&self) -> bool {
+		self.history.lock().unwrap().building
+	}
+
+	/// The most recent builds, oldest first.
+	pub fn builds(&self) -> Vec<BuildRecord> {
+		self.history.lock().unwrap().builds.iter().cloned().collect()
+	}
+
+	pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+		self.sender.subscribe()
+	}
+}
@@ -0,0 +1,106 @@
+use crate::{pattern::PathPattern, rules_file::RulesFile, server};
+use actix_web::{
+	body::MessageBody,
+	dev::{ServiceRequest, ServiceResponse},
+	http::header::{HeaderName, HeaderValue},
+	middleware::Next,
+	web,
+};
+use serde::Deserialize;
+use std::{collections::BTreeMap, path::Path};
+
+pub const FILE_NAME: &str = "_headers";
+
+/// Headers to add to every response whose path matches a pattern.
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HeaderRule {
+	#[serde(rename = "for")]
+	pub path: PathPattern,
+	pub values: BTreeMap<String, String>,
+}
+
+/// The rules from the config and from the `_headers` file in the HTML root.
+pub struct HeaderRules {
+	pub configured: Vec<HeaderRule>,
+	pub file: Option<RulesFile<HeaderRule>>,
+}
+
+/// Reads a Netlify `_headers` file: a path pattern on its own line,
+/// followed by indented `Name: value` lines.
+pub fn parse(text: &str, source: &Path) -> Vec<HeaderRule> {
+	let mut rules: Vec<HeaderRule> = Vec::new();
+	for (number, line) in text.lines().enumerate() {
+		let trimmed = line.trim();
+		if trimmed.is_empty() || trimmed.starts_with('#') {
+			continue;
+		}
+		let warn = |message: &str| log::warn!("{}:{}: {}", source.display(), number + 1, message);
+
+		if !line.starts_with(char::is_whitespace) {
+			match PathPattern::parse(trimmed) {
+				Ok(path) => rules.push(HeaderRule {
+					path,
+					values: BTreeMap::new(),
+				}),
+				Err(error) => warn(&error),
+			}
+			continue;
+		}
+
+		let rule = match rules.last_mut() {
+			Some(rule) => rule,
+			None => {
+				warn("header before the first path");
+				continue;
+			}
+		};
+		match trimmed.split_once(':') {
+			Some((name, value)) => {
+				let value = value.trim();
+				// repeating a header joins the values, the way Netlify does
+				rule.values
+					.entry(name.trim().to_string())
+					.and_modify(|existing| {
+						existing.push_str(", ");
+						existing.push_str(value);
+					})
+					.or_insert_with(|| value.to_string());
+			}
+			None => warn("expected `Name: value`"),
+		}
+	}
+	rules
+}
+
+/// Adds the headers of every matching rule, except to the tools' own endpoints,
+/// which a policy written for the app could break.
+pub async fn middleware(
+	req: ServiceRequest,
+	next: Next<impl MessageBody>,
+) -> actix_web::Result<ServiceResponse<impl MessageBody>> {
+	let rules = req
+		.app_data::<web::Data<HeaderRules>>()
+		.filter(|_| !server::is_tools_path(req.path()))
+		.cloned();
+	let path = req.path().to_string();
+	let mut res = next.call(req).await?;
+
+	if let Some(rules) = rules {
+		let file = rules.file.as_ref().map(RulesFile::rules).unwrap_or_default();
+		for rule in rules.configured.iter().chain(file.iter()) {
+			if !rule.path.matches(&path) {
+				continue;
+			}
+			for (name, value) in &rule.values {
+				match (HeaderName::from_bytes(name.as_bytes()), HeaderValue::from_str(value)) {
+					(Ok(name), Ok(value)) => {
+						res.headers_mut().insert(name, value);
+					}
+					_ => log::warn!("skipping invalid header `{}: {}` for {}", name, value, rule.path),
+				}
+			}
+		}
+	}
+	Ok(res)
+}
@@ -0,0 +1,73 @@
+use crate::server::{self, Settings};
+use actix_web::{
+	body::{self, BoxBody, MessageBody},
+	dev::{ServiceRequest, ServiceResponse},
+	http::{
+		header::{CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE},
+		StatusCode,
+	},
+	middleware::Next,
+	web, HttpResponse,
+};
+use std::ptr;
+
+pub const CLIENT_PATH: &str = "/__cui/client.js";
+const CLIENT: &str = include_str!("client/client.js");
+const MISSING_PAGE: &str = "<!DOCTYPE html><html><head><title>Not found</title></head><body><p>This page does not exist, or the app has not finished building yet.</p></body></html>";
+const TAG: &str = r#"<script type="module" src="/__cui/client.js"></script>"#;
+
+pub async fn client(settings: web::Data<Settings>) -> HttpResponse {
+	HttpResponse::Ok()
+		.content_type("text/javascript; charset=utf-8")
+		.body(format!("const FORWARD_CONSOLE = {};\n{}", settings.forward_console, CLIENT))
+}
+
+/// Stands in for pages that don't exist, usually because the app has not built yet.
+pub async fn missing_page() -> HttpResponse {
+	HttpResponse::NotFound()
+		.content_type("text/html; charset=utf-8")
+		.body(MISSING_PAGE)
+}
+
+/// Adds the dev client script to the HTML mount's pages, right before `</body>` when there is one.
+pub async fn middleware(
+	req: ServiceRequest,
+	next: Next<impl MessageBody + 'static>,
+) -> actix_web::Result<ServiceResponse<BoxBody>> {
+	// the dashboard and proxied backends are not app pages to reload, and proxied bodies stream through
+	let page = !server::is_tools_path(req.path())
+		&& req.app_data::<web::Data<Settings>>().is_some_and(|settings| {
+			!settings.is_proxied(req.path())
+				&& settings.mount_for(req.path()).is_some_and(|mount| ptr::eq(mount, &settings.html))
+		});
+	let res = next.call(req).await?;
+	if !page || !is_plain_html(&res) {
+		return Ok(res.map_into_boxed_body());
+	}
+
+	let (req, res) = res.into_parts();
+	let (mut res, body) = res.into_parts();
+	let html = body::to_bytes(body)
+		.await
+		.map_err(|_| actix_web::error::ErrorInternalServerError("cannot read response body"))?;
+	let html = String::from_utf8_lossy(&html);
+	let html = match html.rfind("</body>") {
+		Some(index) => format!("{}{}{}", &html[..index], TAG, &html[index..]),
+		None => format!("{}{}", html, TAG),
+	};
+
+	res.headers_mut().remove(CONTENT_LENGTH);
+	let res = res.set_body(html).map_into_boxed_body();
+	Ok(ServiceResponse::new(req, res))
+}
+
+fn is_plain_html<B>(res: &ServiceResponse<B>) -> bool {
+	let headers = res.headers();
+	res.status() != StatusCode::PARTIAL_CONTENT
+		&& res.status() != StatusCode::NOT_MODIFIED
+		&& !headers.contains_key(CONTENT_ENCODING)
+		&& headers
+			.get(CONTENT_TYPE)
+			.and_then(|value| value.to_str().ok())
+			.is_some_and(|value| value.starts_with("text/html"))
+}
@@ -0,0 +1,59 @@
+//! Builds, serves and live reloads CUI apps. [`DevServer`] runs the dev server of `cui-tools dev`
+//! inside other programs and tests.
+
+mod access;
+mod cache;
+mod cli;
+mod commands;
+mod compress;
+pub mod config;
+mod console;
+mod dashboard;
+pub mod dev_server;
+pub mod diagnostics;
+mod dist;
+pub mod error;
+pub mod events;
+mod headers;
+mod inject;
+mod listing;
+mod mocks;
+mod pattern;
+mod proxy;
+mod redirects;
+mod reload;
+mod rules_file;
+pub mod server;
+mod spa;
+mod throttle;
+mod tls;
+mod tui;
+mod wasm_pack;
+mod watch;
+
+pub use dev_server::{DevServer, DevServerHandle, DevService};
+
+use clap::Parser;
+use std::{env, process};
+
+/// The `cui-tools` command line, which the binary only calls.
+#[doc(hidden)]
+pub fn main() {
+	let cli = cli::Cli::parse();
+	match cli.tui() {
+		true => tui::init_logger(cli.log_level()),
+		false => env_logger::init_from_env(env_logger::Env::new().default_filter_or(cli.log_level())),
+	}
+
+	if let Some(dir) = &cli.dir {
+		if let Err(error) = env::set_current_dir(dir) {
+			log::error!("cannot change directory to {}: {}", dir.display(), error);
+			process::exit(error::exit::USAGE);
+		}
+	}
+
+	if let Err(error) = commands::run(cli) {
+		log::error!("{}", error);
+		process::exit(error.exit_code());
+	}
+}
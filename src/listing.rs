@@ -0,0 +1,130 @@
+use crate::dist;
+use actix_files::Directory;
+use actix_web::{
+	dev::ServiceResponse,
+	http::header::HttpDate,
+	HttpRequest, HttpResponse,
+};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::{
+	io,
+	time::{Duration, UNIX_EPOCH},
+};
+
+/// What a mount answers for a directory without an `index.html`.
+#[derive(Clone, Copy, Debug, Deserialize, ValueEnum, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Listing {
+	/// No listing, a 404
+	Off,
+	/// A plain list of links
+	On,
+	/// The entries as JSON, with sizes and modification times
+	Json,
+	/// A table with sizes and modification times
+	Styled,
+}
+
+#[derive(Serialize)]
+struct Entry {
+	name: String,
+	dir: bool,
+	/// Zero for directories.
+	size: u64,
+	/// Seconds since the Unix epoch.
+	modified: Option<u64>,
+}
+
+pub fn json(dir: &Directory, req: &HttpRequest) -> io::Result<ServiceResponse> {
+	let res = HttpResponse::Ok().json(entries(dir)?);
+	Ok(ServiceResponse::new(req.clone(), res))
+}
+
+pub fn styled(dir: &Directory, req: &HttpRequest) -> io::Result<ServiceResponse> {
+	let base = req.path().trim_end_matches('/');
+	let mut rows = String::new();
+	if !base.is_empty() {
+		rows.push_str(r#"<tr><td><a href="../">../</a></td><td></td><td></td></tr>"#);
+	}
+	for entry in entries(dir)? {
+		let (suffix, size) = match entry.dir {
+			true => ("/", String::new()),
+			false => ("", dist::format_size(entry.size)),
+		};
+		let modified = entry
+			.modified
+			.map(|seconds| HttpDate::from(UNIX_EPOCH + Duration::from_secs(seconds)).to_string())
+			.unwrap_or_default();
+		rows.push_str(&format!(
+			r#"<tr><td><a href="{}/{}{}">{}{}</a></td><td class="size">{}</td><td>{}</td></tr>"#,
+			base,
+			encode(&entry.name),
+			suffix,
+			escape(&entry.name),
+			suffix,
+			size,
+			modified,
+		));
+	}
+
+	let title = format!("Index of {}", escape(req.path()));
+	let html = format!(
+		r#"<!DOCTYPE html><html><head><meta charset="utf-8"><title>{title}</title><style>{style}</style></head><body><h1>{title}</h1><table><thead><tr><th>Name</th><th>Size</th><th>Modified</th></tr></thead><tbody>{rows}</tbody></table></body></html>"#,
+		title = title,
+		style = STYLE,
+		rows = rows,
+	);
+	let res = HttpResponse::Ok().content_type("text/html; charset=utf-8").body(html);
+	Ok(ServiceResponse::new(req.clone(), res))
+}
+
+const STYLE: &str = "body{font:14px system-ui,sans-serif;margin:2em;color:#222}\
+	h1{font-size:1.2em}table{border-collapse:collapse;min-width:40em}\
+	th,td{text-align:left;padding:.3em 1.5em .3em 0;border-bottom:1px solid #eee}\
+	th{color:#777;font-weight:normal}.size{text-align:right;font-variant-numeric:tabular-nums}\
+	a{color:#0b57d0;text-decoration:none}a:hover{text-decoration:underline}";
+
+/// The visible entries of a directory, subdirectories first.
+fn entries(dir: &Directory) -> io::Result<Vec<Entry>> {
+	let mut entries = Vec::new();
+	for entry in dir.path.read_dir()? {
+		if !dir.is_visible(&entry) {
+			continue;
+		}
+		let entry = entry?;
+		let metadata = match entry.metadata() {
+			Ok(metadata) => metadata,
+			Err(_) => continue,
+		};
+		entries.push(Entry {
+			name: entry.file_name().to_string_lossy().into_owned(),
+			dir: metadata.is_dir(),
+			size: if metadata.is_dir() { 0 } else { metadata.len() },
+			modified: metadata
+				.modified()
+				.ok()
+				.and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+				.map(|since| since.as_secs()),
+		});
+	}
+	entries.sort_by(|a, b| b.dir.cmp(&a.dir).then_with(|| a.name.cmp(&b.name)));
+	Ok(entries)
+}
+
+fn escape(text: &str) -> String {
+	text.replace('&', "&amp;")
+		.replace('<', "&lt;")
+		.replace('>', "&gt;")
+		.replace('"', "&quot;")
+}
+
+/// Percent-encodes a file name for use in a URL path.
+fn encode(name: &str) -> String {
+	name.bytes()
+		.map(|byte| match byte {
+			b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (byte as char).to_string(),
+			_ => format!("%{:02X}", byte),
+		})
+		.collect()
+}
@@ -0,0 +1,298 @@
+use crate::{
+	pattern::{Captures, PathPattern},
+	server,
+};
+use actix_web::{
+	body::{BoxBody, MessageBody},
+	dev::{ServiceRequest, ServiceResponse},
+	http::{
+		header::{HeaderName, HeaderValue, CONTENT_TYPE},
+		StatusCode,
+	},
+	middleware::Next,
+	web, HttpResponse,
+};
+use serde::Deserialize;
+use serde_json::Value;
+use std::{
+	collections::BTreeMap,
+	fs, io,
+	path::{Path, PathBuf},
+	sync::{Arc, Mutex},
+	time::{Duration, Instant, SystemTime},
+};
+
+/// Where fixtures are looked for, relative to the config file.
+pub const DIR: &str = "mocks";
+/// How long the fixtures are used before the directory is looked at again.
+const FRESH_FOR: Duration = Duration::from_millis(500);
+
+/// A canned response for requests matching `method` and `path`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Mock {
+	/// Any method when left out.
+	pub method: Option<String>,
+	pub path: PathPattern,
+	#[serde(default = "default_status")]
+	pub status: u16,
+	#[serde(default)]
+	pub headers: BTreeMap<String, String>,
+	#[serde(default)]
+	pub delay_ms: u64,
+	/// Strings are sent as text, anything else as JSON. `:name` in strings is
+	/// replaced with what the placeholder of the same name in `path` matched.
+	#[serde(default)]
+	pub body: Value,
+}
+
+fn default_status() -> u16 {
+	200
+}
+
+/// Fixture files with their modification times.
+type Files = Vec<(PathBuf, SystemTime)>;
+
+/// The fixtures in a directory, read again soon after a file in it changes.
+pub struct Mocks {
+	dir: PathBuf,
+	cached: Mutex<Option<Cached>>,
+}
+
+struct Cached {
+	checked: Instant,
+	files: Files,
+	mocks: Arc<Vec<Mock>>,
+}
+
+impl Mocks {
+	pub fn new(dir: PathBuf) -> Self {
+		Self {
+			dir,
+			cached: Mutex::new(None),
+		}
+	}
+
+	/// The fixtures, unless the directory is due to be looked at again.
+	pub fn fresh(&self) -> Option<Arc<Vec<Mock>>> {
+		self.cached
+			.lock()
+			.unwrap()
+			.as_ref()
+			.filter(|cached| cached.checked.elapsed() < FRESH_FOR)
+			.map(|cached| cached.mocks.clone())
+	}
+
+	/// The fixtures as they are on disk now, which takes walking the directory.
+	pub fn mocks(&self) -> Arc<Vec<Mock>> {
+		let mut files = Vec::new();
+		if walk(&self.dir, &mut files).is_err() {
+			return Arc::new(Vec::new());
+		}
+		files.sort();
+
+		let mut cached = self.cached.lock().unwrap();
+		if let Some(cached) = cached.as_mut().filter(|cached| cached.files == files) {
+			cached.checked = Instant::now();
+			return cached.mocks.clone();
+		}
+
+		let mut mocks = Vec::new();
+		for (path, _) in &files {
+			match read(path) {
+				Ok(read) => mocks.extend(read),
+				Err(error) => log::warn!("skipping {}: {}", path.display(), error),
+			}
+		}
+		log::info!("loaded {} mocks from {}", mocks.len(), self.dir.display());
+		let mocks = Arc::new(mocks);
+		*cached = Some(Cached {
+			checked: Instant::now(),
+			files,
+			mocks: mocks.clone(),
+		});
+		mocks
+	}
+}
+
+/// Reads a fixture file, which holds either one mock or a list of them.
+fn read(path: &Path) -> Result<Vec<Mock>, String> {
+	let text = fs::read_to_string(path).map_err(|error| error.to_string())?;
+	let value: Value = match path.extension().and_then(|extension| extension.to_str()) {
+		Some("json") => serde_json::from_str(&text).map_err(|error| error.to_string())?,
+		_ => serde_yaml::from_str(&text).map_err(|error| error.to_string())?,
+	};
+	let mocks: Vec<Mock> = match value {
+		Value::Array(_) => serde_json::from_value(value),
+		_ => serde_json::from_value(value).map(|mock| vec![mock]),
+	}
+	.map_err(|error| error.to_string())?;
+
+	for mock in &mocks {
+		if StatusCode::from_u16(mock.status).is_err() {
+			return Err(format!("invalid status {} for {}", mock.status, mock.path));
+		}
+		for (name, value) in &mock.headers {
+			if HeaderName::from_bytes(name.as_bytes()).is_err() || HeaderValue::from_str(value).is_err() {
+				return Err(format!("invalid header `{}: {}` for {}", name, value, mock.path));
+			}
+		}
+	}
+	Ok(mocks)
+}
+
+/// The first mock for `method` and `path`, with what its placeholders captured.
+fn find<'a>(mocks: &'a [Mock], method: &str, path: &str) -> Option<(&'a Mock, Captures)> {
+	mocks.iter().find_map(|mock| {
+		let method_matches = mock
+			.method
+			.as_ref()
+			.is_none_or(|expected| expected.eq_ignore_ascii_case(method));
+		let captures = mock.path.captures(path).filter(|_| method_matches)?;
+		Some((mock, captures))
+	})
+}
+
+/// Answers requests that match a fixture, before the proxies and mounts see them.
+pub async fn middleware(
+	req: ServiceRequest,
+	next: Next<impl MessageBody + 'static>,
+) -> actix_web::Result<ServiceResponse<BoxBody>> {
+	let mocks = match req.app_data::<web::Data<Mocks>>() {
+		Some(mocks) if !server::is_tools_path(req.path()) => match mocks.fresh() {
+			Some(fresh) => fresh,
+			None => {
+				let mocks = mocks.clone();
+				web::block(move || mocks.mocks()).await.unwrap_or_default()
+			}
+		},
+		_ => Arc::default(),
+	};
+	let (mock, captures) = match find(&mocks, req.method().as_str(), req.path()) {
+		Some(found) => found,
+		None => return next.call(req).await.map(ServiceResponse::map_into_boxed_body),
+	};
+
+	log::debug!("mocking {} {} with {}", req.method(), req.path(), mock.path);
+	if mock.delay_ms > 0 {
+		tokio::time::sleep(Duration::from_millis(mock.delay_ms)).await;
+	}
+
+	let mut res = HttpResponse::build(StatusCode::from_u16(mock.status).unwrap_or(StatusCode::OK));
+	let body = match expand(&mock.body, &|text| captures.expand(text)) {
+		Value::Null => String::new(),
+		Value::String(text) => {
+			res.content_type("text/plain; charset=utf-8");
+			text
+		}
+		body => {
+			res.content_type("application/json");
+			body.to_string()
+		}
+	};
+	for (name, value) in &mock.headers {
+		if name.eq_ignore_ascii_case(CONTENT_TYPE.as_str()) {
+			res.insert_header((name.as_str(), captures.expand(value)));
+		} else {
+			res.append_header((name.as_str(), captures.expand(value)));
+		}
+	}
+	Ok(req.into_response(res.body(body)))
+}
+
+/// Applies `template` to every string in a JSON value.
+fn expand(value: &Value, template: &dyn Fn(&str) -> String) -> Value {
+	match value {
+		Value::String(text) => Value::String(template(text)),
+		Value::Array(values) => Value::Array(values.iter().map(|value| expand(value, template)).collect()),
+		Value::Object(fields) => Value::Object(
+			fields
+				.iter()
+				.map(|(name, value)| (name.clone(), expand(value, template)))
+				.collect(),
+		),
+		value => value.clone(),
+	}
+}
+
+fn walk(dir: &Path, files: &mut Files) -> io::Result<()> {
+	for entry in fs::read_dir(dir)? {
+		let entry = entry?;
+		let path = entry.path();
+		if entry.file_type()?.is_dir() {
+			walk(&path, files)?;
+		} else if path
+			.extension()
+			.is_some_and(|extension| extension == "json" || extension == "yaml" || extension == "yml")
+		{
+			files.push((path, entry.metadata()?.modified()?));
+		}
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+
+	fn fixture(name: &str, text: &str) -> Result<Vec<Mock>, String> {
+		let dir = std::env::temp_dir().join(format!("cui-mocks-{}", std::process::id()));
+		fs::create_dir_all(&dir).unwrap();
+		let path = dir.join(name);
+		fs::write(&path, text).unwrap();
+		let mocks = read(&path);
+		fs::remove_file(path).unwrap();
+		mocks
+	}
+
+	#[test]
+	fn reads_one_mock_or_a_list() {
+		let mocks = fixture("users.yaml", "path: /api/users\nbody: [1, 2]\n").unwrap();
+		assert_eq!(mocks.len(), 1);
+		assert_eq!(mocks[0].status, 200);
+		assert_eq!(mocks[0].body, json!([1, 2]));
+
+		let mocks = fixture(
+			"orders.json",
+			r#"[{"path": "/api/orders", "method": "POST", "status": 201}, {"path": "/api/orders/:id"}]"#,
+		)
+		.unwrap();
+		assert_eq!(mocks.len(), 2);
+		assert_eq!(mocks[0].method.as_deref(), Some("POST"));
+		assert_eq!(mocks[1].body, Value::Null);
+	}
+
+	#[test]
+	fn invalid_fixtures_are_refused_when_read() {
+		assert!(fixture("status.yaml", "path: /a\nstatus: 1000\n").is_err());
+		assert!(fixture("name.yaml", "path: /a\nheaders:\n  \"bad name\": x\n").is_err());
+		assert!(fixture("value.yaml", "path: /a\nheaders:\n  x-value: \"a\\nb\"\n").is_err());
+		assert!(fixture("field.yaml", "path: /a\nbdoy: x\n").is_err());
+		assert!(fixture("path.yaml", "path: a\n").is_err());
+	}
+
+	#[test]
+	fn matches_method_and_path() {
+		let mocks = fixture(
+			"users.yaml",
+			"- path: /api/users/:id\n  method: delete\n  status: 204\n- path: /api/users/:id\n",
+		)
+		.unwrap();
+		let (mock, captures) = find(&mocks, "DELETE", "/api/users/7").unwrap();
+		assert_eq!(mock.status, 204);
+		assert_eq!(captures.expand(":id"), "7");
+		assert_eq!(find(&mocks, "GET", "/api/users/7").unwrap().0.status, 200);
+		assert!(find(&mocks, "GET", "/api/users").is_none());
+	}
+
+	#[test]
+	fn fills_in_the_body() {
+		let captures = PathPattern::parse("/api/users/:id").unwrap().captures("/api/users/7").unwrap();
+		let body = json!({"id": ":id", "name": "user :id", "identifier": ":identifier", "tags": [":id", 1], "admin": false});
+		assert_eq!(
+			expand(&body, &|text| captures.expand(text)),
+			json!({"id": "7", "name": "user 7", "identifier": ":identifier", "tags": ["7", 1], "admin": false})
+		);
+	}
+}
@@ -0,0 +1,188 @@
+use serde::{Deserialize, Deserializer};
+use std::fmt;
+
+/// A Netlify-style path pattern: `/blog/:year/*` matches `/blog/2022/a/b`,
+/// capturing `year` as `2022` and `splat` as `a/b`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PathPattern {
+	source: String,
+	segments: Vec<Segment>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Segment {
+	Literal(String),
+	Placeholder(String),
+	/// Only allowed last, matches the rest of the path including nothing at all.
+	Splat,
+}
+
+/// Values captured by placeholders and the splat, by name.
+#[derive(Debug, Default)]
+pub struct Captures(Vec<(String, String)>);
+
+impl PathPattern {
+	pub fn parse(source: &str) -> Result<Self, String> {
+		let source = source.trim();
+		if !source.starts_with('/') {
+			return Err(format!("`{}` must start with `/`", source));
+		}
+
+		let parts: Vec<_> = source.trim_start_matches('/').split('/').collect();
+		let mut segments = Vec::new();
+		for (index, part) in parts.iter().enumerate() {
+			segments.push(match *part {
+				"*" if index + 1 == parts.len() => Segment::Splat,
+				"*" => return Err(format!("`*` can only be at the end of `{}`", source)),
+				"" if index + 1 == parts.len() => continue,
+				_ => match part.strip_prefix(':') {
+					Some(name) => Segment::Placeholder(name.to_string()),
+					None => Segment::Literal(part.to_string()),
+				},
+			});
+		}
+
+		Ok(Self {
+			source: source.to_string(),
+			segments,
+		})
+	}
+
+	pub fn matches(&self, path: &str) -> bool {
+		self.captures(path).is_some()
+	}
+
+	pub fn captures(&self, path: &str) -> Option<Captures> {
+		let mut parts = path.trim_start_matches('/').split('/').filter(|part| !part.is_empty());
+		let mut captures = Captures::default();
+		for segment in &self.segments {
+			match segment {
+				Segment::Splat => {
+					let rest: Vec<_> = parts.collect();
+					captures.0.push(("splat".into(), rest.join("/")));
+					return Some(captures);
+				}
+				Segment::Literal(literal) => {
+					if parts.next()? != literal {
+						return None;
+					}
+				}
+				Segment::Placeholder(name) => captures.0.push((name.clone(), parts.next()?.to_string())),
+			}
+		}
+		match parts.next() {
+			Some(_) => None,
+			None => Some(captures),
+		}
+	}
+}
+
+impl Captures {
+	/// Replaces `:name` and `:splat` in `template` with the captured values, where the name
+	/// is not the start of a longer word, so `:id` leaves `:identifier` alone.
+	pub fn expand(&self, template: &str) -> String {
+		// longest names first, so `:id` does not eat the start of a captured `:id_2`
+		let mut captures: Vec<_> = self.0.iter().collect();
+		captures.sort_by_key(|(name, _)| std::cmp::Reverse(name.len()));
+		let is_word = |c: char| c.is_ascii_alphanumeric() || c == '_';
+
+		let mut result = String::with_capacity(template.len());
+		let mut rest = template;
+		while let Some(index) = rest.find(':') {
+			result.push_str(&rest[..index]);
+			let after = &rest[index + 1..];
+			let found = captures.iter().find(|(name, _)| {
+				after.starts_with(name.as_str()) && !after[name.len()..].starts_with(is_word)
+			});
+			match found {
+				Some((name, value)) => {
+					result.push_str(value);
+					rest = &after[name.len()..];
+				}
+				None => {
+					result.push(':');
+					rest = after;
+				}
+			}
+		}
+		result.push_str(rest);
+		result
+	}
+}
+
+impl fmt::Display for PathPattern {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str(&self.source)
+	}
+}
+
+impl<'de> Deserialize<'de> for PathPattern {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		let source = String::deserialize(deserializer)?;
+		PathPattern::parse(&source).map_err(serde::de::Error::custom)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn captures(pattern: &str, path: &str) -> Option<Vec<(String, String)>> {
+		PathPattern::parse(pattern).unwrap().captures(path).map(|captures| captures.0)
+	}
+
+	#[test]
+	fn literals_match_exactly() {
+		assert!(captures("/about", "/about").is_some());
+		assert!(captures("/about", "/about/").is_some());
+		assert!(captures("/about", "/about/team").is_none());
+		assert!(captures("/about", "/contact").is_none());
+	}
+
+	#[test]
+	fn placeholders_capture_one_segment() {
+		assert_eq!(
+			captures("/blog/:year/:slug", "/blog/2022/hello"),
+			Some(vec![("year".into(), "2022".into()), ("slug".into(), "hello".into())])
+		);
+		assert!(captures("/blog/:year/:slug", "/blog/2022").is_none());
+		assert!(captures("/blog/:year/:slug", "/blog/2022/hello/more").is_none());
+	}
+
+	#[test]
+	fn splat_captures_the_rest() {
+		assert_eq!(
+			captures("/blog/:year/*", "/blog/2022/a/b"),
+			Some(vec![("year".into(), "2022".into()), ("splat".into(), "a/b".into())])
+		);
+		assert_eq!(captures("/docs/*", "/docs"), Some(vec![("splat".into(), "".into())]));
+		assert_eq!(captures("/*", "/"), Some(vec![("splat".into(), "".into())]));
+	}
+
+	#[test]
+	fn splat_only_at_the_end() {
+		assert!(PathPattern::parse("/*/edit").is_err());
+		assert!(PathPattern::parse("relative").is_err());
+	}
+
+	#[test]
+	fn expand_fills_in_captures() {
+		let captures = PathPattern::parse("/old/:id/*").unwrap().captures("/old/7/x/y").unwrap();
+		assert_eq!(captures.expand("/new/:id/:splat"), "/new/7/x/y");
+		assert_eq!(captures.expand("/static"), "/static");
+	}
+
+	#[test]
+	fn expand_prefers_longer_names() {
+		let captures = PathPattern::parse("/:id/:identifier").unwrap().captures("/1/name").unwrap();
+		assert_eq!(captures.expand("/:identifier/:id"), "/name/1");
+	}
+
+	#[test]
+	fn expand_leaves_longer_words_alone() {
+		let captures = PathPattern::parse("/api/users/:id").unwrap().captures("/api/users/7").unwrap();
+		assert_eq!(captures.expand(":identifier"), ":identifier");
+		assert_eq!(captures.expand(":id_card :id-card :id."), ":id_card 7-card 7.");
+		assert_eq!(captures.expand("http://host/:id"), "http://host/7");
+	}
+}
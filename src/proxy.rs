@@ -0,0 +1,376 @@
+use crate::server::normalize_prefix;
+use actix_web::{
+	body::SizedStream,
+	error::ErrorBadGateway,
+	http::{
+		header::{self, HeaderMap, HeaderName, HeaderValue},
+		Uri,
+	},
+	rt, web, HttpRequest, HttpResponse,
+};
+use actix_ws::{CloseReason, Message};
+use awc::{ws::Frame, Client};
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Deserializer};
+use std::collections::BTreeMap;
+
+/// Headers that only describe one connection, and must not be passed on.
+const HOP_BY_HOP: &[&str] = &[
+	"connection",
+	"keep-alive",
+	"proxy-authenticate",
+	"proxy-authorization",
+	"te",
+	"trailer",
+	"transfer-encoding",
+	"upgrade",
+];
+/// Headers of the WebSocket handshake, which the client makes again for the backend.
+const HANDSHAKE: &[&str] = &[
+	"sec-websocket-key",
+	"sec-websocket-version",
+	"sec-websocket-extensions",
+	"sec-websocket-protocol",
+];
+const MAX_FRAME_SIZE: usize = 16 * 1024 * 1024;
+
+/// Forwards requests under `prefix`, WebSocket upgrades included, to a backend.
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Proxy {
+	#[serde(deserialize_with = "prefix")]
+	pub prefix: String,
+	#[serde(deserialize_with = "target")]
+	pub target: String,
+	/// Remove `prefix` from the path, so `/api/users` goes to `<target>/users`.
+	#[serde(default)]
+	pub strip_prefix: bool,
+	/// Headers to set on the forwarded requests, an empty value removes the header.
+	#[serde(default)]
+	pub headers: BTreeMap<String, String>,
+}
+
+impl Proxy {
+	/// Reads a `PREFIX=URL` command line argument.
+	pub fn parse(arg: &str) -> Result<Self, String> {
+		let (prefix, target) = arg
+			.split_once('=')
+			.ok_or_else(|| format!("expected `PREFIX=URL`, found `{}`", arg))?;
+		Ok(Self {
+			prefix: normalize_prefix(prefix),
+			target: parse_target(target)?,
+			strip_prefix: false,
+			headers: BTreeMap::new(),
+		})
+	}
+
+	pub fn matches(&self, path: &str) -> bool {
+		path.strip_prefix(self.prefix.trim_end_matches('/'))
+			.is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+	}
+
+	/// The backend URL for a request path and query.
+	fn url(&self, path: &str, query: &str) -> String {
+		let path = match self.strip_prefix {
+			true => &path[self.prefix.trim_end_matches('/').len()..],
+			false => path,
+		};
+		let mut url = format!("{}/{}", self.target, path.trim_start_matches('/'));
+		if !query.is_empty() {
+			url.push('?');
+			url.push_str(query);
+		}
+		url
+	}
+
+	/// Points redirects to the backend's own address back at the proxy.
+	fn rewrite_location(&self, location: &str) -> Option<String> {
+		let path = location.strip_prefix(&self.target)?;
+		if !(path.is_empty() || path.starts_with('/') || path.starts_with('?')) {
+			return None;
+		}
+		Some(match self.strip_prefix {
+			true => format!("{}{}", self.prefix.trim_end_matches('/'), path),
+			false => path.to_string(),
+		})
+	}
+}
+
+fn prefix<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+	Ok(normalize_prefix(&String::deserialize(deserializer)?))
+}
+
+fn target<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+	parse_target(&String::deserialize(deserializer)?).map_err(serde::de::Error::custom)
+}
+
+fn parse_target(target: &str) -> Result<String, String> {
+	let uri = target
+		.parse::<Uri>()
+		.map_err(|error| format!("invalid proxy target `{}`: {}", target, error))?;
+	match (uri.scheme_str(), uri.authority()) {
+		(Some("http"), Some(_)) => Ok(target.trim_end_matches('/').to_string()),
+		_ => Err(format!("proxy target `{}` must be an http:// URL", target)),
+	}
+}
+
+/// Sends a request on to the backend and streams its response back.
+pub async fn forward(
+	req: HttpRequest,
+	body: web::Payload,
+	proxy: web::Data<Proxy>,
+	client: web::Data<Client>,
+) -> actix_web::Result<HttpResponse> {
+	if is_websocket(&req) {
+		return bridge(req, body, proxy, client).await;
+	}
+
+	let url = proxy.url(req.path(), req.query_string());
+	log::debug!("proxying {} to {}", req.path(), url);
+	let mut request = client.request(req.method().clone(), &url).no_decompress();
+	for (name, value) in forwarded_headers(&req, &proxy, &[]) {
+		request = request.append_header((name, value));
+	}
+
+	let length = req
+		.headers()
+		.get(header::CONTENT_LENGTH)
+		.and_then(|value| value.to_str().ok())
+		.and_then(|value| value.parse().ok());
+	let sent = match length {
+		Some(length) => request.send_body(SizedStream::new(length, body)).await,
+		None if req.headers().contains_key(header::TRANSFER_ENCODING) => request.send_stream(body).await,
+		None => request.send().await,
+	};
+	let backend = sent.map_err(|error| {
+		log::warn!("proxy to {} failed: {}", url, error);
+		ErrorBadGateway(format!("cannot reach {}", proxy.target))
+	})?;
+
+	let mut res = HttpResponse::build(backend.status());
+	for (name, value) in backend.headers() {
+		if is_hop_by_hop(name) || name == header::CONTENT_LENGTH {
+			continue;
+		}
+		let location = (name == header::LOCATION)
+			.then(|| value.to_str().ok().and_then(|value| proxy.rewrite_location(value)))
+			.flatten()
+			.and_then(|location| HeaderValue::from_str(&location).ok());
+		res.append_header((name.clone(), location.unwrap_or_else(|| value.clone())));
+	}
+	Ok(res.streaming(backend))
+}
+
+/// Connects to the backend's WebSocket and passes messages both ways until either side closes.
+async fn bridge(
+	req: HttpRequest,
+	body: web::Payload,
+	proxy: web::Data<Proxy>,
+	client: web::Data<Client>,
+) -> actix_web::Result<HttpResponse> {
+	let url = proxy.url(req.path(), req.query_string()).replacen("http", "ws", 1);
+	log::debug!("proxying WebSocket {} to {}", req.path(), url);
+	let mut request = client.ws(&url).max_frame_size(MAX_FRAME_SIZE);
+	for (name, value) in forwarded_headers(&req, &proxy, HANDSHAKE) {
+		request = request.header(name, value);
+	}
+	if let Some(protocols) = req
+		.headers()
+		.get(header::SEC_WEBSOCKET_PROTOCOL)
+		.and_then(|value| value.to_str().ok())
+	{
+		request = request.protocols(protocols.split(',').map(str::trim));
+	}
+
+	let (handshake, backend) = request.connect().await.map_err(|error| {
+		log::warn!("proxy to {} failed: {}", url, error);
+		ErrorBadGateway(format!("cannot reach {}", proxy.target))
+	})?;
+	let (mut res, mut session, browser) = actix_ws::handle(&req, body)?;
+	if let Some(protocol) = handshake.headers().get(header::SEC_WEBSOCKET_PROTOCOL) {
+		res.headers_mut().insert(header::SEC_WEBSOCKET_PROTOCOL, protocol.clone());
+	}
+
+	let mut browser = browser.max_frame_size(MAX_FRAME_SIZE);
+	let (mut sink, mut backend) = backend.split();
+	rt::spawn(async move {
+		let reason: Option<CloseReason> = loop {
+			tokio::select! {
+				message = browser.recv() => match message {
+					Some(Ok(Message::Close(reason))) => {
+						let _ = sink.send(Message::Close(reason.clone())).await;
+						break reason;
+					}
+					Some(Ok(message)) => {
+						if sink.send(message).await.is_err() {
+							break None;
+						}
+					}
+					Some(Err(_)) | None => break None,
+				},
+				frame = backend.next() => {
+					let sent = match frame {
+						Some(Ok(Frame::Text(bytes))) => session.text(String::from_utf8_lossy(&bytes).into_owned()).await,
+						Some(Ok(Frame::Binary(bytes))) => session.binary(bytes).await,
+						Some(Ok(Frame::Continuation(item))) => session.continuation(item).await,
+						Some(Ok(Frame::Ping(bytes))) => session.ping(&bytes).await,
+						Some(Ok(Frame::Pong(bytes))) => session.pong(&bytes).await,
+						Some(Ok(Frame::Close(reason))) => break reason,
+						Some(Err(_)) | None => break None,
+					};
+					if sent.is_err() {
+						break None;
+					}
+				}
+			}
+		};
+		let _ = session.close(reason).await;
+		let _ = sink.close().await;
+	});
+	Ok(res)
+}
+
+/// The request's headers minus the ones for this hop, plus `X-Forwarded-*` and the configured ones.
+fn forwarded_headers(req: &HttpRequest, proxy: &Proxy, skip: &[&str]) -> HeaderMap {
+	let mut headers = HeaderMap::new();
+	for (name, value) in req.headers() {
+		if is_hop_by_hop(name) || name == header::HOST || skip.contains(&name.as_str()) {
+			continue;
+		}
+		headers.append(name.clone(), value.clone());
+	}
+
+	let info = req.connection_info();
+	let forwarded_for = match (req.headers().get("x-forwarded-for"), req.peer_addr()) {
+		(Some(existing), Some(peer)) => format!("{}, {}", existing.to_str().unwrap_or_default(), peer.ip()),
+		(None, Some(peer)) => peer.ip().to_string(),
+		(existing, None) => existing
+			.and_then(|existing| existing.to_str().ok())
+			.unwrap_or_default()
+			.to_string(),
+	};
+	let forwarded = [
+		("x-forwarded-for", forwarded_for.as_str()),
+		("x-forwarded-host", info.host()),
+		("x-forwarded-proto", info.scheme()),
+	];
+	let configured = proxy.headers.iter().map(|(name, value)| (name.as_str(), value.as_str()));
+	for (name, value) in forwarded.iter().copied().chain(configured) {
+		match (HeaderName::from_bytes(name.as_bytes()), HeaderValue::from_str(value)) {
+			// an empty value removes the header
+			(Ok(name), Ok(value)) if value.is_empty() => {
+				headers.remove(name);
+			}
+			(Ok(name), Ok(value)) => {
+				headers.insert(name, value);
+			}
+			_ => log::warn!("skipping invalid header `{}: {}` for {}", name, value, proxy.prefix),
+		}
+	}
+	headers
+}
+
+fn is_websocket(req: &HttpRequest) -> bool {
+	req.headers()
+		.get(header::UPGRADE)
+		.and_then(|value| value.to_str().ok())
+		.is_some_and(|value| value.eq_ignore_ascii_case("websocket"))
+}
+
+fn is_hop_by_hop(name: &HeaderName) -> bool {
+	HOP_BY_HOP.contains(&name.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use actix_web::{
+		test::{call_service, init_service, read_body, TestRequest},
+		App, HttpServer,
+	};
+
+	fn proxy(strip_prefix: bool) -> Proxy {
+		Proxy {
+			strip_prefix,
+			..Proxy::parse("/api=http://localhost:3000").unwrap()
+		}
+	}
+
+	#[test]
+	fn urls_keep_or_strip_the_prefix() {
+		assert_eq!(proxy(false).url("/api/users", ""), "http://localhost:3000/api/users");
+		assert_eq!(proxy(false).url("/api/users", "page=2"), "http://localhost:3000/api/users?page=2");
+		assert_eq!(proxy(true).url("/api/users", "page=2"), "http://localhost:3000/users?page=2");
+		assert_eq!(proxy(true).url("/api", ""), "http://localhost:3000/");
+	}
+
+	#[test]
+	fn redirects_to_the_backend_come_back_to_the_proxy() {
+		assert_eq!(proxy(false).rewrite_location("http://localhost:3000/api/login").as_deref(), Some("/api/login"));
+		assert_eq!(proxy(true).rewrite_location("http://localhost:3000/login?next=/").as_deref(), Some("/api/login?next=/"));
+		assert_eq!(proxy(false).rewrite_location("http://localhost:30001/login"), None);
+		assert_eq!(proxy(false).rewrite_location("https://example.com/"), None);
+	}
+
+	#[test]
+	fn hop_by_hop_headers_stay_behind() {
+		let req = TestRequest::get()
+			.insert_header(("host", "localhost:8080"))
+			.insert_header(("connection", "keep-alive"))
+			.insert_header(("te", "trailers"))
+			.insert_header(("accept", "application/json"))
+			.insert_header(("x-removed", "1"))
+			.to_http_request();
+		let mut proxy = proxy(false);
+		proxy.headers.insert("x-removed".into(), String::new());
+		proxy.headers.insert("x-added".into(), "2".into());
+
+		let headers = forwarded_headers(&req, &proxy, &[]);
+		assert!(!headers.contains_key("connection"));
+		assert!(!headers.contains_key("te"));
+		assert!(!headers.contains_key("host"));
+		assert!(!headers.contains_key("x-removed"));
+		assert_eq!(headers.get("accept").unwrap(), "application/json");
+		assert_eq!(headers.get("x-added").unwrap(), "2");
+		assert_eq!(headers.get("x-forwarded-host").unwrap(), "localhost:8080");
+	}
+
+	#[actix_web::test]
+	async fn forwards_to_the_backend() {
+		let backend = HttpServer::new(|| {
+			App::new().default_service(web::to(|req: HttpRequest| async move {
+				HttpResponse::Ok()
+					.insert_header(("x-backend-path", req.uri().to_string()))
+					.insert_header(("connection", "close"))
+					.body("from the backend")
+			}))
+		})
+		.workers(1)
+		.bind(("127.0.0.1", 0))
+		.unwrap();
+		let address = backend.addrs()[0];
+		let backend = backend.run();
+		let handle = backend.handle();
+		rt::spawn(backend);
+
+		let proxy = Proxy {
+			strip_prefix: true,
+			..Proxy::parse(&format!("/api=http://{}", address)).unwrap()
+		};
+		let app = init_service(
+			App::new().service(
+				web::scope("/api")
+					.app_data(web::Data::new(proxy))
+					.app_data(web::Data::new(Client::default()))
+					.default_service(web::to(forward)),
+			),
+		)
+		.await;
+		let res = call_service(&app, TestRequest::get().uri("/api/users?page=2").to_request()).await;
+		assert_eq!(res.status(), 200);
+		assert_eq!(res.headers().get("x-backend-path").unwrap(), "/users?page=2");
+		assert!(!res.headers().contains_key("connection"));
+		assert_eq!(read_body(res).await, "from the backend");
+		handle.stop(false).await;
+	}
+}
@@ -0,0 +1,261 @@
+use crate::{
+	pattern::PathPattern,
+	rules_file::RulesFile,
+	server::{self, Settings},
+};
+use actix_web::{
+	body::{BoxBody, MessageBody},
+	dev::{ServiceRequest, ServiceResponse},
+	http::{header::LOCATION, StatusCode, Uri},
+	middleware::Next,
+	web, HttpResponse,
+};
+use serde::{Deserialize, Deserializer};
+use std::path::Path;
+
+pub const FILE_NAME: &str = "_redirects";
+
+/// Status codes a rule can have: redirects, rewrites (200) and custom not found pages (404).
+const STATUSES: &[u16] = &[200, 301, 302, 303, 307, 308, 404];
+
+/// Sends requests for `from` to `to`, with `:name` and `:splat` replaced by what `from` captured.
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Redirect {
+	pub from: PathPattern,
+	pub to: String,
+	#[serde(default = "default_status", deserialize_with = "status")]
+	pub status: u16,
+	/// Apply the rule even when a file exists at `from`.
+	#[serde(default)]
+	pub force: bool,
+}
+
+/// The rules from the config and from the `_redirects` file in the HTML root.
+pub struct RedirectRules {
+	pub configured: Vec<Redirect>,
+	pub file: Option<RulesFile<Redirect>>,
+}
+
+impl Redirect {
+	fn is_rewrite(&self) -> bool {
+		self.status == 200 || self.status == 404
+	}
+}
+
+fn default_status() -> u16 {
+	301
+}
+
+fn status<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u16, D::Error> {
+	let status = u16::deserialize(deserializer)?;
+	match STATUSES.contains(&status) {
+		true => Ok(status),
+		false => Err(serde::de::Error::custom(format!("unsupported status {}", status))),
+	}
+}
+
+/// Reads a Netlify `_redirects` file: `from to [status][!]` on each line,
+/// where `!` applies the rule even when a file exists at `from`.
+pub fn parse(text: &str, source: &Path) -> Vec<Redirect> {
+	let mut rules = Vec::new();
+	for (number, line) in text.lines().enumerate() {
+		let line = line.trim();
+		if line.is_empty() || line.starts_with('#') {
+			continue;
+		}
+		let warn = |message: &str| log::warn!("{}:{}: {}", source.display(), number + 1, message);
+
+		let fields: Vec<_> = line.split_whitespace().collect();
+		let (from, to, status) = match fields[..] {
+			[from, to] => (from, to, "301"),
+			[from, to, status] => (from, to, status),
+			[_, _, _, ..] => {
+				warn("query and condition matching are not supported");
+				continue;
+			}
+			_ => {
+				warn("expected `from to [status]`");
+				continue;
+			}
+		};
+		let from = match PathPattern::parse(from) {
+			Ok(from) => from,
+			Err(error) => {
+				warn(&error);
+				continue;
+			}
+		};
+		let (status, force) = match status.strip_suffix('!') {
+			Some(status) => (status, true),
+			None => (status, false),
+		};
+		let status = match status.parse() {
+			Ok(status) if STATUSES.contains(&status) => status,
+			_ => {
+				warn(&format!("unsupported status `{}`", status));
+				continue;
+			}
+		};
+		let rule = Redirect {
+			from,
+			to: to.to_string(),
+			status,
+			force,
+		};
+		if rule.is_rewrite() && !rule.to.starts_with('/') {
+			warn("rewrites can only go to paths on this server");
+			continue;
+		}
+		rules.push(rule);
+	}
+	rules
+}
+
+/// Applies the first matching rule before the `Files` services resolve the path.
+/// Like Netlify, rules without `force` don't apply to paths that have a file, or a directory listing.
+/// The tools' own endpoints are left alone, so a catch-all rule can't break live reload.
+pub async fn middleware(
+	mut req: ServiceRequest,
+	next: Next<impl MessageBody + 'static>,
+) -> actix_web::Result<ServiceResponse<BoxBody>> {
+	if server::is_tools_path(req.path()) {
+		return next.call(req).await.map(ServiceResponse::map_into_boxed_body);
+	}
+	let found = match (
+		req.app_data::<web::Data<RedirectRules>>(),
+		req.app_data::<web::Data<Settings>>(),
+	) {
+		(Some(rules), Some(settings)) => find(rules, settings, req.path()),
+		_ => None,
+	};
+	let (rule, mut target) = match found {
+		Some(found) => found,
+		None => return next.call(req).await.map(ServiceResponse::map_into_boxed_body),
+	};
+	if !target.contains('?') && !req.query_string().is_empty() {
+		target = format!("{}?{}", target, req.query_string());
+	}
+
+	if !rule.is_rewrite() {
+		let status = StatusCode::from_u16(rule.status).unwrap_or(StatusCode::MOVED_PERMANENTLY);
+		let res = HttpResponse::build(status).insert_header((LOCATION, target)).finish();
+		return Ok(req.into_response(res));
+	}
+
+	let uri = match target.parse::<Uri>() {
+		Ok(uri) => uri,
+		Err(error) => {
+			log::warn!("cannot rewrite {} to {}: {}", req.path(), target, error);
+			return next.call(req).await.map(ServiceResponse::map_into_boxed_body);
+		}
+	};
+	log::debug!("rewriting {} to {}", req.path(), uri);
+	req.head_mut().uri = uri.clone();
+	req.match_info_mut().get_mut().update(&uri);
+
+	let mut res = next.call(req).await?.map_into_boxed_body();
+	if rule.status == 404 && res.status().is_success() {
+		*res.response_mut().status_mut() = StatusCode::NOT_FOUND;
+	}
+	Ok(res)
+}
+
+fn find(rules: &RedirectRules, settings: &Settings, path: &str) -> Option<(Redirect, String)> {
+	// the backend behind a proxy has its own routes
+	if settings.is_proxied(path) {
+		return None;
+	}
+	let file = rules.file.as_ref().map(RulesFile::rules).unwrap_or_default();
+	let mut exists = None;
+	for rule in rules.configured.iter().chain(file.iter()) {
+		let captures = match rule.from.captures(path) {
+			Some(captures) => captures,
+			None => continue,
+		};
+		if !rule.force && *exists.get_or_insert_with(|| settings.file_for(path).is_some() || settings.lists_dir(path)) {
+			continue;
+		}
+		return Some((rule.clone(), captures.expand(&rule.to)));
+	}
+	None
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::proxy::Proxy;
+	use std::fs;
+
+	fn rules(text: &str) -> RedirectRules {
+		RedirectRules {
+			configured: parse(text, Path::new(FILE_NAME)),
+			file: None,
+		}
+	}
+
+	#[test]
+	fn parses_status_and_force() {
+		let rules = parse(
+			"# comment\n\n/a /b\n/c /d 302\n/e /f 200!\n/g /h 999\n/i /j 301 Country=nl\n/k\n",
+			Path::new(FILE_NAME),
+		);
+		let parsed: Vec<_> = rules
+			.iter()
+			.map(|rule| (rule.from.to_string(), rule.to.as_str(), rule.status, rule.force))
+			.collect();
+		assert_eq!(
+			parsed,
+			[
+				("/a".to_string(), "/b", 301, false),
+				("/c".to_string(), "/d", 302, false),
+				("/e".to_string(), "/f", 200, true),
+			]
+		);
+	}
+
+	#[test]
+	fn rewrites_stay_on_this_server() {
+		assert!(parse("/api/* https://example.com/:splat 200", Path::new(FILE_NAME)).is_empty());
+		assert_eq!(parse("/api/* https://example.com/:splat", Path::new(FILE_NAME)).len(), 1);
+	}
+
+	#[test]
+	fn expands_splat_and_placeholders() {
+		let rules = rules("/blog/:year/* /posts/:year/:splat 301");
+		let (rule, target) = find(&rules, &Settings::default(), "/blog/2022/a/b").unwrap();
+		assert_eq!(rule.status, 301);
+		assert_eq!(target, "/posts/2022/a/b");
+		assert!(find(&rules, &Settings::default(), "/other").is_none());
+	}
+
+	#[test]
+	fn not_found_pages_are_rewrites() {
+		let rules = rules("/* /404.html 404");
+		let (rule, target) = find(&rules, &Settings::default(), "/missing/page").unwrap();
+		assert!(rule.is_rewrite());
+		assert_eq!(target, "/404.html");
+	}
+
+	#[test]
+	fn existing_files_win_unless_forced() {
+		let dir = std::env::temp_dir().join(format!("cui-redirects-{}", std::process::id()));
+		fs::create_dir_all(&dir).unwrap();
+		fs::write(dir.join("page.html"), "").unwrap();
+		let mut settings = Settings::default();
+		settings.html.dir = dir.clone();
+
+		assert!(find(&rules("/* /index.html 200"), &settings, "/page.html").is_none());
+		assert!(find(&rules("/* /index.html 200"), &settings, "/missing").is_some());
+		assert!(find(&rules("/* /index.html 200!"), &settings, "/page.html").is_some());
+		fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[test]
+	fn proxied_paths_are_left_to_the_backend() {
+		let mut settings = Settings::default();
+		settings.proxies.push(Proxy::parse("/api=http://localhost:3000").unwrap());
+		assert!(find(&rules("/* /index.html 200!"), &settings, "/api/users").is_none());
+		assert!(find(&rules("/* /index.html 200!"), &settings, "/apis").is_some());
+	}
+}
@@ -0,0 +1,81 @@
+use crate::{
+	access, dashboard,
+	events::{Event, Events},
+};
+use actix_web::{rt, web, HttpRequest, HttpResponse};
+use actix_ws::Message;
+use serde_json::json;
+use tokio::sync::broadcast::error::RecvError;
+
+pub const PATH: &str = "/__cui/ws";
+
+/// Tells connected tabs to reload after a successful build, or why the build failed.
+pub async fn socket(
+	req: HttpRequest,
+	body: web::Payload,
+	events: web::Data<Events>,
+	clients: web::Data<dashboard::State>,
+) -> actix_web::Result<HttpResponse> {
+	// browsers let any site open a WebSocket here, and the build errors show source code
+	if !access::same_origin(&req) {
+		return Ok(HttpResponse::Forbidden().finish());
+	}
+	let (response, mut session, mut messages) = actix_ws::handle(&req, body)?;
+	let client = dashboard::connect(&clients, &req);
+	let last_build = events.last_build();
+	let mut events = events.subscribe();
+
+	rt::spawn(async move {
+		// listed on the dashboard until the task ends
+		let _client = client;
+		// a tab opened after a failed build shows the errors right away
+		if let Some(event @ Event::BuildFailed { .. }) = last_build {
+			if let Some(message) = client_message(&event) {
+				if session.text(message).await.is_err() {
+					return;
+				}
+			}
+		}
+
+		loop {
+			tokio::select! {
+				event = events.recv() => match event {
+					Ok(event) => {
+						if let Some(message) = client_message(&event) {
+							if session.text(message).await.is_err() {
+								return;
+							}
+						}
+					}
+					Err(RecvError::Lagged(_)) => {}
+					Err(RecvError::Closed) => break,
+				},
+				message = messages.recv() => match message {
+					Some(Ok(Message::Ping(bytes))) => {
+						if session.pong(&bytes).await.is_err() {
+							return;
+						}
+					}
+					Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+					Some(Ok(_)) => {}
+				},
+			}
+		}
+		let _ = session.close(None).await;
+	});
+
+	Ok(response)
+}
+
+fn client_message(event: &Event) -> Option<String> {
+	let message = match event {
+		Event::Built { .. } => json!({ "type": "reload" }),
+		Event::BuildFailed { message, diagnostics, .. } => json!({
+			"type": "build-error",
+			"message": message,
+			"diagnostics": diagnostics,
+		}),
+		Event::Building => return None,
+	};
+	Some(message.to_string())
+}
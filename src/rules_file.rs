@@ -0,0 +1,52 @@
+use std::{
+	fs,
+	path::{Path, PathBuf},
+	sync::{Arc, Mutex},
+	time::SystemTime,
+};
+
+/// A rules file like `_headers` that is read again whenever it changes on disk,
+/// so rebuilding the app picks up new rules without restarting the server.
+pub struct RulesFile<T> {
+	path: PathBuf,
+	parse: fn(&str, &Path) -> Vec<T>,
+	cached: Mutex<Option<(SystemTime, Arc<Vec<T>>)>>,
+}
+
+impl<T> RulesFile<T> {
+	pub fn new(path: PathBuf, parse: fn(&str, &Path) -> Vec<T>) -> Self {
+		Self {
+			path,
+			parse,
+			cached: Mutex::new(None),
+		}
+	}
+
+	/// The current rules, none if the file does not exist.
+	pub fn rules(&self) -> Arc<Vec<T>> {
+		let modified = match fs::metadata(&self.path).and_then(|metadata| metadata.modified()) {
+			Ok(modified) => modified,
+			Err(_) => return Arc::new(Vec::new()),
+		};
+
+		let mut cached = self.cached.lock().unwrap();
+		if let Some((read_at, rules)) = &*cached {
+			if *read_at == modified {
+				return rules.clone();
+			}
+		}
+
+		let rules = match fs::read_to_string(&self.path) {
+			Ok(text) => {
+				log::info!("loaded {}", self.path.display());
+				Arc::new((self.parse)(&text, &self.path))
+			}
+			Err(error) => {
+				log::warn!("cannot read {}: {}", self.path.display(), error);
+				Arc::new(Vec::new())
+			}
+		};
+		*cached = Some((modified, rules.clone()));
+		rules
+	}
+}
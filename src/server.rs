@@ -0,0 +1,392 @@
+pub use crate::{
+	cache::CachePolicy, headers::HeaderRule, listing::Listing, pattern::PathPattern, proxy::Proxy,
+	redirects::Redirect, throttle::Throttle,
+};
+use crate::{
+	access, cache, compress, console, dashboard,
+	dev_server::{self, Middleware},
+	diagnostics,
+	events::Events,
+	headers::{self, HeaderRules},
+	inject, listing,
+	mocks::{self, Mocks},
+	proxy,
+	redirects::{self, RedirectRules},
+	reload,
+	rules_file::RulesFile,
+	spa, throttle, tls,
+};
+use actix_files::Files;
+use actix_web::{
+	dev::Server,
+	middleware::{from_fn, Compress, Condition, DefaultHeaders, Logger},
+	web, App, HttpResponse, HttpServer,
+};
+use std::{
+	collections::BTreeMap,
+	io,
+	net::{IpAddr, SocketAddr},
+	path::PathBuf,
+};
+
+/// Paths under this prefix belong to the tools rather than the app.
+pub const TOOLS_PREFIX: &str = "/__cui/";
+
+/// Whether `path` is one of the tools' own endpoints, which app rules must leave alone.
+pub fn is_tools_path(path: &str) -> bool {
+	path.starts_with(TOOLS_PREFIX) || path == dashboard::PATH
+}
+
+#[derive(Clone)]
+pub struct Settings {
+	pub host: String,
+	pub port: u16,
+	pub pkg: Mount,
+	pub html: Mount,
+	pub headers: BTreeMap<String, String>,
+	pub header_rules: Vec<HeaderRule>,
+	/// Also apply the rules in a `_headers` file in the HTML root.
+	pub headers_file: bool,
+	/// Directory of fixture files to answer matching requests with.
+	pub mocks_dir: Option<PathBuf>,
+	pub proxies: Vec<Proxy>,
+	pub redirects: Vec<Redirect>,
+	/// Also apply the rules in a `_redirects` file in the HTML root.
+	pub redirects_file: bool,
+	/// Serve the HTML mount's `index.html` for unknown paths, for apps that route on the client.
+	pub spa: bool,
+	/// Paths that get a 404 instead of `index.html` in SPA mode.
+	pub spa_exclude: Vec<PathPattern>,
+	/// Simulated network conditions, the first rule matching a path applies.
+	pub throttle: Vec<Throttle>,
+	/// Required from other machines, as `?token=` once or an `Authorization: Bearer` header.
+	pub token: Option<String>,
+	/// `USER:PASSWORD` required from other machines.
+	pub basic_auth: Option<String>,
+	/// Serve over TLS, with `cert` and `key` or a certificate generated into `cert_dir`.
+	pub https: bool,
+	pub cert: Option<PathBuf>,
+	pub key: Option<PathBuf>,
+	pub cert_dir: PathBuf,
+	pub live_reload: bool,
+	/// Have the live reload client send the browser console to the terminal.
+	pub forward_console: bool,
+	pub compress: bool,
+	/// Send the headers that enable `SharedArrayBuffer` and wasm threads.
+	pub cross_origin_isolation: bool,
+}
+
+#[derive(Clone)]
+pub struct Mount {
+	pub prefix: String,
+	pub dir: PathBuf,
+	/// Left unset, each command picks the policy that suits it.
+	pub cache: Option<CachePolicy>,
+	pub listing: Listing,
+}
+
+impl Default for Settings {
+	fn default() -> Self {
+		Self {
+			host: "127.0.0.1".into(),
+			port: 8080,
+			pkg: Mount {
+				listing: Listing::On,
+				..Mount::new("/cui", "app/pkg")
+			},
+			html: Mount::new("/", "app/target/html"),
+			headers: BTreeMap::new(),
+			header_rules: Vec::new(),
+			headers_file: true,
+			mocks_dir: None,
+			proxies: Vec::new(),
+			redirects: Vec::new(),
+			redirects_file: true,
+			spa: false,
+			spa_exclude: Vec::new(),
+			throttle: Vec::new(),
+			token: None,
+			basic_auth: None,
+			https: false,
+			cert: None,
+			key: None,
+			cert_dir: tls::CERT_DIR.into(),
+			live_reload: false,
+			forward_console: true,
+			compress: true,
+			cross_origin_isolation: false,
+		}
+	}
+}
+
+impl Mount {
+	pub fn new(prefix: &str, dir: impl Into<PathBuf>) -> Self {
+		Self {
+			prefix: normalize_prefix(prefix),
+			dir: dir.into(),
+			cache: None,
+			listing: Listing::Off,
+		}
+	}
+
+	pub fn cache_policy(&self) -> CachePolicy {
+		self.cache.unwrap_or(CachePolicy::Default)
+	}
+
+	fn files(&self) -> Files {
+		let policy = self.cache_policy();
+		let files = Files::new(&self.prefix, &self.dir)
+			.use_etag(policy.uses_validators())
+			.use_last_modified(policy.uses_validators());
+		match self.listing {
+			Listing::Off => files,
+			Listing::On => files.show_files_listing(),
+			Listing::Json => files.show_files_listing().files_listing_renderer(listing::json),
+			Listing::Styled => files.show_files_listing().files_listing_renderer(listing::styled),
+		}
+	}
+}
+
+impl Settings {
+	/// The mount that serves `path`, preferring the most specific prefix.
+	pub fn mount_for(&self, path: &str) -> Option<&Mount> {
+		let mounts = [&self.pkg, &self.html];
+		mounts
+			.iter()
+			.copied()
+			.filter(|mount| {
+				mount.prefix == "/"
+					|| path == mount.prefix
+					|| path.starts_with(&format!("{}/", mount.prefix))
+			})
+			.max_by_key(|mount| mount.prefix.len())
+	}
+
+	/// The file a mount would serve for `path`, if there is one.
+	/// Paths that need decoding are left to the `Files` services and give `None`.
+	pub fn file_for(&self, path: &str) -> Option<PathBuf> {
+		let (_, mut file) = self.resolve(path)?;
+		if file.is_dir() {
+			file = file.join("index.html");
+		}
+		file.is_file().then_some(file)
+	}
+
+	/// Whether a proxy forwards `path` to its backend instead of a mount answering it.
+	pub fn is_proxied(&self, path: &str) -> bool {
+		self.proxies.iter().any(|proxy| proxy.matches(path))
+	}
+
+	/// Whether a mount answers `path` with a directory listing.
+	pub fn lists_dir(&self, path: &str) -> bool {
+		self.resolve(path)
+			.is_some_and(|(mount, dir)| mount.listing != Listing::Off && dir.is_dir())
+	}
+
+	fn resolve(&self, path: &str) -> Option<(&Mount, PathBuf)> {
+		if path.contains('%') || path.split('/').any(|segment| segment == "..") {
+			return None;
+		}
+		let mount = self.mount_for(path)?;
+		Some((mount, mount.dir.join(path[mount.prefix.len()..].trim_start_matches('/'))))
+	}
+}
+
+pub fn normalize_prefix(prefix: &str) -> String {
+	format!("/{}", prefix.trim_matches('/'))
+}
+
+pub async fn run(settings: Settings, events: Events) -> io::Result<()> {
+	bind(settings, events, Vec::new())?.0.await
+}
+
+/// Binds the server without running it, with `middleware` applied to every request the app gets.
+/// Also gives the addresses it listens on, which tell the port when 0 was asked for.
+pub fn bind(settings: Settings, events: Events, middleware: Vec<Middleware>) -> io::Result<(Server, Vec<SocketAddr>)> {
+	let tls = match settings.https {
+		true => Some(tls::server_config(&settings)?),
+		false => None,
+	};
+	let scheme = if tls.is_some() { "https" } else { "http" };
+	log::info!("starting HTTP server at {}://{}:{}", scheme, settings.host, settings.port);
+	let lan = access::lan_addresses(&settings);
+	if !lan.is_empty() {
+		let query = settings
+			.token
+			.as_ref()
+			.map_or(String::new(), |token| format!("?token={}", token));
+		for address in &lan {
+			let host = match address {
+				IpAddr::V4(_) => address.to_string(),
+				IpAddr::V6(_) => format!("[{}]", address),
+			};
+			log::info!("reachable at {}://{}:{}/{}", scheme, host, settings.port, query);
+		}
+		if settings.token.is_none() && settings.basic_auth.is_none() {
+			log::warn!("anyone on the network can open the app and list its files, pass --token or --basic-auth to stop that");
+		}
+	}
+	log::info!("dashboard at {}://localhost:{}{}", scheme, settings.port, dashboard::PATH);
+	log::info!("serving {} at {}", settings.pkg.dir.display(), settings.pkg.prefix);
+	log::info!("serving {} at {}", settings.html.dir.display(), settings.html.prefix);
+	if let Some(dir) = settings.mocks_dir.as_ref().filter(|dir| dir.is_dir()) {
+		log::info!("serving mocks from {}", dir.display());
+	}
+	for proxy in &settings.proxies {
+		log::info!("proxying {} to {}", proxy.prefix, proxy.target);
+	}
+	if settings.spa {
+		log::info!("serving {}/index.html for unknown paths", settings.html.dir.display());
+	}
+	if settings.cross_origin_isolation {
+		log::info!("serving pages cross-origin isolated");
+	}
+	for rule in &settings.throttle {
+		log::info!(
+			"throttling {}: {} ms latency, {} bandwidth, {}% failures",
+			rule.path,
+			rule.latency_ms,
+			rule.bandwidth_kbps
+				.map_or("unlimited".into(), |kbps| format!("{} kbit/s", kbps)),
+			rule.failure_rate * 100.0,
+		);
+	}
+
+	let bind = (settings.host.clone(), settings.port);
+	let services = Services::new(settings, events, middleware);
+	let server = HttpServer::new(move || {
+		let services = services.clone();
+		App::new()
+			.configure(move |config| services.configure(config))
+			.wrap(Logger::default())
+	});
+	let server = match tls {
+		Some(tls) => server.bind_rustls_0_23(bind, tls)?,
+		None => server.bind(bind)?,
+	};
+	let addresses = server.addrs();
+	Ok((server.run(), addresses))
+}
+
+/// The mounts, the tools' endpoints and the middleware around them, for one app.
+/// Cloned into each worker, which share the state behind it.
+#[derive(Clone)]
+pub(crate) struct Services {
+	settings: web::Data<Settings>,
+	events: web::Data<Events>,
+	header_rules: web::Data<HeaderRules>,
+	redirects: web::Data<RedirectRules>,
+	mocks: Option<web::Data<Mocks>>,
+	dashboard: web::Data<dashboard::State>,
+	middleware: web::Data<Vec<Middleware>>,
+}
+
+impl Services {
+	pub fn new(settings: Settings, events: Events, middleware: Vec<Middleware>) -> Self {
+		let header_rules = HeaderRules {
+			configured: settings.header_rules.clone(),
+			file: settings
+				.headers_file
+				.then(|| RulesFile::new(settings.html.dir.join(headers::FILE_NAME), headers::parse)),
+		};
+		let redirects = RedirectRules {
+			configured: settings.redirects.clone(),
+			file: settings
+				.redirects_file
+				.then(|| RulesFile::new(settings.html.dir.join(redirects::FILE_NAME), redirects::parse)),
+		};
+		Self {
+			mocks: settings.mocks_dir.clone().map(|dir| web::Data::new(Mocks::new(dir))),
+			header_rules: web::Data::new(header_rules),
+			redirects: web::Data::new(redirects),
+			dashboard: web::Data::new(dashboard::State::default()),
+			middleware: web::Data::new(middleware),
+			events: web::Data::new(events),
+			settings: web::Data::new(settings),
+		}
+	}
+
+	/// Registers everything in one scope that takes every path, so routes registered after it never match.
+	pub fn configure(&self, config: &mut web::ServiceConfig) {
+		let settings = &self.settings;
+		config
+			.app_data(self.events.clone())
+			.app_data(settings.clone())
+			.app_data(self.header_rules.clone())
+			.app_data(self.redirects.clone())
+			.app_data(self.dashboard.clone())
+			.app_data(self.middleware.clone());
+		if let Some(mocks) = &self.mocks {
+			config.app_data(mocks.clone());
+		}
+
+		let pkg = settings.pkg.files();
+		let mut html = settings.html.files().index_file("index.html");
+		if settings.spa {
+			html = html.default_handler(web::to(spa::fallback));
+		} else if settings.live_reload {
+			html = html.default_handler(web::to(inject::missing_page));
+		}
+
+		let mut scope = web::scope("")
+			.route(dashboard::PATH, web::get().to(dashboard::page))
+			.route(TOOLS_PREFIX, web::get().to(dashboard::page))
+			.route(dashboard::STATUS_PATH, web::get().to(dashboard::status));
+		if settings.live_reload {
+			scope = scope
+				.route(reload::PATH, web::get().to(reload::socket))
+				.route(inject::CLIENT_PATH, web::get().to(inject::client))
+				.route(console::PATH, web::post().to(console::endpoint))
+				.route(diagnostics::PATH, web::get().to(diagnostics::endpoint));
+		}
+		// like on Netlify, the rules files configure the server and are not served themselves
+		for name in &[headers::FILE_NAME, redirects::FILE_NAME] {
+			let path = format!("{}/{}", settings.html.prefix.trim_end_matches('/'), name);
+			scope = scope.route(&path, web::route().to(HttpResponse::NotFound));
+		}
+		// proxies are registered before the mounts so they can take paths from under them,
+		// and the middleware that rewrites or answers for the mounts leaves their paths alone
+		let client = web::Data::new(awc::Client::builder().disable_redirects().disable_timeout().finish());
+		for proxy in &settings.proxies {
+			scope = scope.service(
+				web::scope(proxy.prefix.trim_end_matches('/'))
+					.app_data(web::Data::new(proxy.clone()))
+					.app_data(client.clone())
+					.default_service(web::to(proxy::forward)),
+			);
+		}
+		// the more specific mount has to be registered first
+		let scope = if settings.pkg.prefix.len() >= settings.html.prefix.len() {
+			scope.service(pkg).service(html)
+		} else {
+			scope.service(html).service(pkg)
+		};
+		let headers = settings
+			.headers
+			.iter()
+			.fold(DefaultHeaders::new(), |headers, (name, value)| {
+				headers.add((name.as_str(), value.as_str()))
+			});
+		config.service(
+			scope
+				.wrap(from_fn(compress::middleware))
+				.wrap(from_fn(mocks::middleware))
+				.wrap(from_fn(redirects::middleware))
+				.wrap(Condition::new(settings.live_reload, from_fn(inject::middleware)))
+				.wrap(headers)
+				.wrap(Condition::new(
+					settings.cross_origin_isolation,
+					DefaultHeaders::new()
+						.add(("Cross-Origin-Opener-Policy", "same-origin"))
+						.add(("Cross-Origin-Embedder-Policy", "require-corp")),
+				))
+				.wrap(from_fn(cache::middleware))
+				.wrap(from_fn(headers::middleware))
+				.wrap(Condition::new(settings.compress, Compress::default()))
+				.wrap(from_fn(throttle::middleware))
+				.wrap(from_fn(dev_server::middleware))
+				.wrap(from_fn(dashboard::middleware))
+				.wrap(from_fn(access::middleware)),
+		);
+	}
+}
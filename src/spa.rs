@@ -0,0 +1,27 @@
+use crate::{inject, server::Settings};
+use actix_files::NamedFile;
+use actix_web::{http::Method, web, HttpRequest, HttpResponse};
+
+/// Answers requests the HTML mount has no file for with its `index.html`,
+/// so the app can route them on the client.
+pub async fn fallback(req: HttpRequest, settings: web::Data<Settings>) -> actix_web::Result<HttpResponse> {
+	if is_route(&req, &settings) {
+		if let Ok(index) = NamedFile::open_async(settings.html.dir.join("index.html")).await {
+			return Ok(index.into_response(&req));
+		}
+	}
+	Ok(match settings.live_reload {
+		true => inject::missing_page().await,
+		false => HttpResponse::NotFound().finish(),
+	})
+}
+
+/// Whether a request looks like a page of the app: a GET without a file extension
+/// that no exclude pattern matches. Missing assets should still be a 404.
+fn is_route(req: &HttpRequest, settings: &Settings) -> bool {
+	let path = req.path();
+	let name = path.rsplit('/').next().unwrap_or_default();
+	(req.method() == Method::GET || req.method() == Method::HEAD)
+		&& !name.contains('.')
+		&& !settings.spa_exclude.iter().any(|pattern| pattern.matches(path))
+}
@@ -0,0 +1,148 @@
+use crate::{pattern::PathPattern, server};
+use actix_web::{
+	body::{BodySize, BoxBody, MessageBody},
+	dev::{ServiceRequest, ServiceResponse},
+	middleware::Next,
+	web::{self, Bytes},
+	HttpResponse,
+};
+use serde::{Deserialize, Deserializer};
+use std::{
+	error::Error,
+	future::Future,
+	pin::Pin,
+	task::{Context, Poll},
+	time::Duration,
+};
+use tokio::time::{Instant, Sleep};
+
+/// The longest a throttled body sends nothing for.
+const TICK: Duration = Duration::from_millis(100);
+
+/// Slows down or fails responses whose path matches a pattern, to see how the app copes with a bad network.
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Throttle {
+	#[serde(rename = "for", default = "everything")]
+	pub path: PathPattern,
+	/// Time to wait before handling the request.
+	#[serde(default)]
+	pub latency_ms: u64,
+	/// Kilobits per second to send the response body at.
+	pub bandwidth_kbps: Option<u64>,
+	/// Share of requests, from 0 to 1, to answer with `503 Service Unavailable`.
+	#[serde(default, deserialize_with = "rate")]
+	pub failure_rate: f64,
+}
+
+impl Throttle {
+	/// A rule for every path, for the command line flags.
+	pub fn everywhere(latency_ms: u64, bandwidth_kbps: Option<u64>, failure_rate: f64) -> Self {
+		Self {
+			path: everything(),
+			latency_ms,
+			bandwidth_kbps,
+			failure_rate,
+		}
+	}
+}
+
+fn everything() -> PathPattern {
+	PathPattern::parse("/*").unwrap()
+}
+
+fn rate<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+	let rate = f64::deserialize(deserializer)?;
+	check_rate(rate).map_err(serde::de::Error::custom)
+}
+
+/// Reads a failure rate from the command line.
+pub fn parse_rate(arg: &str) -> Result<f64, String> {
+	check_rate(arg.parse().map_err(|_| format!("invalid failure rate `{}`", arg))?)
+}
+
+fn check_rate(rate: f64) -> Result<f64, String> {
+	match (0.0..=1.0).contains(&rate) {
+		true => Ok(rate),
+		false => Err(format!("failure rate {} must be between 0 and 1", rate)),
+	}
+}
+
+/// Applies the first rule matching the path. The tools' own endpoints are left alone.
+pub async fn middleware(
+	req: ServiceRequest,
+	next: Next<impl MessageBody + 'static>,
+) -> actix_web::Result<ServiceResponse<BoxBody>> {
+	let rule = req
+		.app_data::<web::Data<server::Settings>>()
+		.filter(|_| !server::is_tools_path(req.path()))
+		.and_then(|settings| settings.throttle.iter().find(|rule| rule.path.matches(req.path())).cloned());
+	let rule = match rule {
+		Some(rule) => rule,
+		None => return next.call(req).await.map(ServiceResponse::map_into_boxed_body),
+	};
+
+	if rule.latency_ms > 0 {
+		tokio::time::sleep(Duration::from_millis(rule.latency_ms)).await;
+	}
+	if rule.failure_rate > 0.0 && fastrand::f64() < rule.failure_rate {
+		log::debug!("failing {} on purpose", req.path());
+		let res = HttpResponse::ServiceUnavailable().body("simulated failure");
+		return Ok(req.into_response(res));
+	}
+
+	let res = next.call(req).await?.map_into_boxed_body();
+	Ok(match rule.bandwidth_kbps {
+		Some(kbps) => res.map_body(|_, body| BoxBody::new(Throttled::new(body, kbps))),
+		None => res,
+	})
+}
+
+/// A body that waits after each piece for as long as sending it would take at the given rate.
+struct Throttled {
+	body: BoxBody,
+	bytes_per_second: u64,
+	bytes_per_tick: usize,
+	pending: Bytes,
+	sleep: Pin<Box<Sleep>>,
+}
+
+impl Throttled {
+	fn new(body: BoxBody, kbps: u64) -> Self {
+		let bytes_per_second = (kbps * 1000 / 8).max(1);
+		Self {
+			body,
+			bytes_per_second,
+			bytes_per_tick: (bytes_per_second * TICK.as_millis() as u64 / 1000).max(1) as usize,
+			pending: Bytes::new(),
+			sleep: Box::pin(tokio::time::sleep(Duration::ZERO)),
+		}
+	}
+}
+
+impl MessageBody for Throttled {
+	type Error = Box<dyn Error>;
+
+	/// Keeps the original size, so the browser can still show progress.
+	fn size(&self) -> BodySize {
+		self.body.size()
+	}
+
+	fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Bytes, Self::Error>>> {
+		let this = self.get_mut();
+		if this.sleep.as_mut().poll(cx).is_pending() {
+			return Poll::Pending;
+		}
+		if this.pending.is_empty() {
+			match Pin::new(&mut this.body).poll_next(cx) {
+				Poll::Ready(Some(Ok(bytes))) => this.pending = bytes,
+				other => return other,
+			}
+		}
+
+		let piece = this.pending.split_to(this.bytes_per_tick.min(this.pending.len()));
+		let wait = Duration::from_micros(piece.len() as u64 * 1_000_000 / this.bytes_per_second);
+		this.sleep.as_mut().reset(Instant::now() + wait);
+		Poll::Ready(Some(Ok(piece)))
+	}
+}
@@ -0,0 +1,76 @@
+use crate::{access, server::Settings};
+use rustls::{
+	crypto::ring,
+	pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer},
+	ServerConfig,
+};
+use std::{
+	fs, io,
+	net::IpAddr,
+	path::{Path, PathBuf},
+	sync::Arc,
+};
+
+/// Where generated certificates are kept, relative to the config file.
+pub const CERT_DIR: &str = "target/cui-tools";
+
+/// The rustls config for the certificate in the settings, or a self-signed one for this machine.
+pub fn server_config(settings: &Settings) -> io::Result<ServerConfig> {
+	let (cert, key) = match (&settings.cert, &settings.key) {
+		(Some(cert), Some(key)) => (cert.clone(), key.clone()),
+		(None, None) => generate(&settings.cert_dir, &names(settings))?,
+		_ => return Err(invalid("a certificate and its key have to be given together".into())),
+	};
+
+	let certs = CertificateDer::pem_file_iter(&cert)
+		.and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+		.map_err(|error| invalid(format!("cannot read certificate {}: {}", cert.display(), error)))?;
+	let key = PrivateKeyDer::from_pem_file(&key)
+		.map_err(|error| invalid(format!("cannot read key {}: {}", key.display(), error)))?;
+
+	ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+		.with_safe_default_protocol_versions()
+		.and_then(|builder| builder.with_no_client_auth().with_single_cert(certs, key))
+		.map_err(|error| invalid(error.to_string()))
+}
+
+/// The host names and addresses a generated certificate is valid for.
+fn names(settings: &Settings) -> Vec<String> {
+	let mut names = vec!["localhost".to_string(), "127.0.0.1".into(), "::1".into()];
+	let unspecified = settings
+		.host
+		.parse::<IpAddr>()
+		.is_ok_and(|address| address.is_unspecified());
+	if !unspecified && !names.contains(&settings.host) {
+		names.push(settings.host.clone());
+	}
+	names.extend(access::lan_addresses(settings).iter().map(IpAddr::to_string));
+	names
+}
+
+/// Writes a self-signed certificate to `dir`, or reuses the one there if it covers the same names.
+fn generate(dir: &Path, names: &[String]) -> io::Result<(PathBuf, PathBuf)> {
+	let cert = dir.join("cert.pem");
+	let key = dir.join("key.pem");
+	let names_file = dir.join("names");
+	let listed = names.join("\n");
+	if cert.is_file() && key.is_file() && fs::read_to_string(&names_file).is_ok_and(|read| read == listed) {
+		return Ok((cert, key));
+	}
+
+	log::info!("generating a self-signed certificate for {}", names.join(", "));
+	let generated = rcgen::generate_simple_self_signed(names.to_vec()).map_err(|error| invalid(error.to_string()))?;
+	fs::create_dir_all(dir)?;
+	fs::write(&cert, generated.cert.pem())?;
+	fs::write(&key, generated.key_pair.serialize_pem())?;
+	fs::write(&names_file, listed)?;
+	log::info!(
+		"browsers will warn about {} until it is trusted, or pass --cert and --key to use your own",
+		cert.display()
+	);
+	Ok((cert, key))
+}
+
+fn invalid(message: String) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidInput, message)
+}
@@ -0,0 +1,309 @@
+use crate::events::Event;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use ratatui::{
+	crossterm::event::{self, Event as TermEvent, KeyCode, KeyEventKind, KeyModifiers},
+	layout::{Constraint, Direction, Layout, Rect},
+	style::{Color, Modifier, Style},
+	text::{Line, Span},
+	widgets::{Block, Borders, Paragraph},
+	DefaultTerminal, Frame,
+};
+use std::{
+	collections::VecDeque,
+	io,
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc, Mutex,
+	},
+	thread::{self, JoinHandle},
+	time::Duration,
+};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+/// Lines kept per pane.
+const MAX_LINES: usize = 1000;
+/// How often the screen is redrawn when nothing is typed.
+const TICK: Duration = Duration::from_millis(100);
+const REQUEST_TARGET: &str = "actix_web::middleware::logger";
+
+/// What the panes show, filled by the logger while the interface is up.
+static PANES: Mutex<Panes> = Mutex::new(Panes {
+	active: false,
+	verbose: false,
+	status: None,
+	build: VecDeque::new(),
+	requests: VecDeque::new(),
+	console: VecDeque::new(),
+});
+
+struct Panes {
+	active: bool,
+	/// Whether lines only the verbose filter lets through are shown.
+	verbose: bool,
+	/// The latest build event, for the status line.
+	status: Option<Event>,
+	build: VecDeque<Entry>,
+	requests: VecDeque<Entry>,
+	console: VecDeque<Entry>,
+}
+
+struct Entry {
+	/// `None` for build output, which is not logged.
+	level: Option<Level>,
+	text: String,
+	verbose: bool,
+}
+
+impl Panes {
+	fn push(&mut self, target: &str, level: Option<Level>, text: &str, verbose: bool) {
+		let pane = match target {
+			"browser" => &mut self.console,
+			_ if target.starts_with(REQUEST_TARGET) => &mut self.requests,
+			_ => &mut self.build,
+		};
+		for line in text.lines() {
+			if pane.len() == MAX_LINES {
+				pane.pop_front();
+			}
+			pane.push_back(Entry {
+				level,
+				text: line.replace('\t', "    "),
+				verbose,
+			});
+		}
+	}
+
+	fn clear(&mut self) {
+		self.build.clear();
+		self.requests.clear();
+		self.console.clear();
+	}
+}
+
+/// Sends log records to the panes while the interface is up, and to the terminal as usual otherwise.
+/// Records only `l` shows are kept too, so toggling it also reveals what came before.
+struct Logger {
+	normal: env_logger::Logger,
+	verbose: env_logger::filter::Filter,
+}
+
+impl Log for Logger {
+	fn enabled(&self, metadata: &Metadata) -> bool {
+		self.normal.enabled(metadata) || self.verbose.enabled(metadata)
+	}
+
+	fn log(&self, record: &Record) {
+		let normal = self.normal.matches(record);
+		let mut panes = PANES.lock().unwrap();
+		if !panes.active {
+			drop(panes);
+			self.normal.log(record);
+		} else if normal || self.verbose.matches(record) {
+			panes.push(record.target(), Some(record.level()), &record.args().to_string(), !normal);
+		}
+	}
+
+	fn flush(&self) {
+		self.normal.flush();
+	}
+}
+
+/// Installs the logger for `dev --tui`, filtered like `env_logger` with `level` as the default.
+pub fn init_logger(level: &str) {
+	let env = env_logger::Env::new().default_filter_or(level);
+	let normal = env_logger::Builder::from_env(env).build();
+	let filters = std::env::var("RUST_LOG").unwrap_or_else(|_| level.into());
+	let verbose = env_logger::filter::Builder::new()
+		.parse(&format!("{},cui_tools=debug,browser=debug", filters))
+		.build();
+	log::set_max_level(normal.filter().max(verbose.filter()));
+	if log::set_boxed_logger(Box::new(Logger { normal, verbose })).is_err() {
+		log::set_max_level(LevelFilter::Off);
+	}
+}
+
+/// Prints build tool output, into the build pane while the interface is up.
+pub fn print_build_output(text: &str) {
+	let mut panes = PANES.lock().unwrap();
+	match panes.active {
+		true => panes.push("", None, text, false),
+		false => {
+			drop(panes);
+			eprintln!("{}", text.trim_end_matches('\n'));
+		}
+	}
+}
+
+/// Keeps the status line current, as a [`DevServer::on_event`](crate::DevServer::on_event) hook.
+pub fn show_event(event: &Event) {
+	PANES.lock().unwrap().status = Some(event.clone());
+}
+
+/// What a key asks of the dev server.
+pub enum Action {
+	Rebuild,
+	Quit,
+}
+
+/// The full-screen interface of `dev --tui`, drawn on its own thread until dropped.
+pub struct Tui {
+	stop: Arc<AtomicBool>,
+	thread: Option<JoinHandle<()>>,
+	actions: UnboundedReceiver<Action>,
+}
+
+impl Tui {
+	pub fn start(url: String) -> io::Result<Self> {
+		let mut terminal = ratatui::try_init()?;
+		PANES.lock().unwrap().active = true;
+		let stop = Arc::new(AtomicBool::new(false));
+		let (sender, actions) = mpsc::unbounded_channel();
+		let stopped = stop.clone();
+		let thread = thread::spawn(move || {
+			if let Err(error) = run(&mut terminal, &url, &sender, &stopped) {
+				PANES.lock().unwrap().active = false;
+				let _ = ratatui::try_restore();
+				log::error!("terminal interface failed: {}", error);
+				let _ = sender.send(Action::Quit);
+			}
+		});
+		Ok(Self {
+			stop,
+			thread: Some(thread),
+			actions,
+		})
+	}
+
+	/// The next key press the dev server has to act on.
+	pub async fn next(&mut self) -> Action {
+		self.actions.recv().await.unwrap_or(Action::Quit)
+	}
+}
+
+impl Drop for Tui {
+	fn drop(&mut self) {
+		self.stop.store(true, Ordering::Relaxed);
+		if let Some(thread) = self.thread.take() {
+			let _ = thread.join();
+		}
+		PANES.lock().unwrap().active = false;
+		let _ = ratatui::try_restore();
+	}
+}
+
+fn run(
+	terminal: &mut DefaultTerminal,
+	url: &str,
+	actions: &UnboundedSender<Action>,
+	stop: &AtomicBool,
+) -> io::Result<()> {
+	while !stop.load(Ordering::Relaxed) {
+		terminal.draw(|frame| draw(frame, url))?;
+		if !event::poll(TICK)? {
+			continue;
+		}
+		let key = match event::read()? {
+			TermEvent::Key(key) if key.kind == KeyEventKind::Press => key,
+			_ => continue,
+		};
+		match key.code {
+			KeyCode::Char('q') => {
+				let _ = actions.send(Action::Quit);
+			}
+			KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+				let _ = actions.send(Action::Quit);
+			}
+			KeyCode::Char('r') => {
+				log::info!("rebuilding");
+				let _ = actions.send(Action::Rebuild);
+			}
+			KeyCode::Char('c') => PANES.lock().unwrap().clear(),
+			KeyCode::Char('l') => {
+				let mut panes = PANES.lock().unwrap();
+				panes.verbose = !panes.verbose;
+			}
+			_ => {}
+		}
+	}
+	Ok(())
+}
+
+fn draw(frame: &mut Frame, url: &str) {
+	let rows = Layout::default()
+		.direction(Direction::Vertical)
+		.constraints([
+			Constraint::Length(1),
+			Constraint::Percentage(50),
+			Constraint::Min(3),
+			Constraint::Length(1),
+		])
+		.split(frame.area());
+	let columns = Layout::default()
+		.direction(Direction::Horizontal)
+		.constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+		.split(rows[2]);
+
+	let panes = PANES.lock().unwrap();
+	frame.render_widget(status(panes.status.as_ref(), url, panes.verbose), rows[0]);
+	pane(frame, "Build", &panes.build, panes.verbose, rows[1]);
+	pane(frame, "Requests", &panes.requests, panes.verbose, columns[0]);
+	pane(frame, "Browser console", &panes.console, panes.verbose, columns[1]);
+
+	let key = Style::default().add_modifier(Modifier::BOLD);
+	let help = Line::from(vec![
+		Span::styled(" r", key),
+		Span::raw(" rebuild  "),
+		Span::styled("c", key),
+		Span::raw(" clear  "),
+		Span::styled("l", key),
+		Span::raw(" log level  "),
+		Span::styled("q", key),
+		Span::raw(" quit"),
+	]);
+	frame.render_widget(Paragraph::new(help).style(Style::default().fg(Color::DarkGray)), rows[3]);
+}
+
+fn status(event: Option<&Event>, url: &str, verbose: bool) -> Paragraph<'static> {
+	let build = match event {
+		Some(Event::Building) => Span::styled("building", Style::default().fg(Color::Yellow)),
+		Some(Event::Built { duration_ms, .. }) => Span::styled(
+			format!("built in {:.1}s", *duration_ms as f32 / 1000.0),
+			Style::default().fg(Color::Green),
+		),
+		Some(Event::BuildFailed { .. }) => Span::styled("build failed", Style::default().fg(Color::Red)),
+		_ => Span::raw("not built"),
+	};
+	Paragraph::new(Line::from(vec![
+		Span::styled(" cui-tools ", Style::default().add_modifier(Modifier::BOLD)),
+		Span::raw(format!("{}  ", url)),
+		build,
+		Span::styled(
+			format!("  log: {}", if verbose { "verbose" } else { "normal" }),
+			Style::default().fg(Color::DarkGray),
+		),
+	]))
+}
+
+/// The newest lines that fit, oldest at the top.
+fn pane(frame: &mut Frame, title: &str, entries: &VecDeque<Entry>, verbose: bool, area: Rect) {
+	let height = area.height.saturating_sub(2) as usize;
+	let mut lines: Vec<Line> = entries
+		.iter()
+		.rev()
+		.filter(|entry| verbose || !entry.verbose)
+		.take(height)
+		.map(|entry| Line::styled(entry.text.clone(), style(entry.level)))
+		.collect();
+	lines.reverse();
+	let block = Block::default().borders(Borders::ALL).title(format!(" {} ", title));
+	frame.render_widget(Paragraph::new(lines).block(block), area);
+}
+
+fn style(level: Option<Level>) -> Style {
+	match level {
+		Some(Level::Error) => Style::default().fg(Color::Red),
+		Some(Level::Warn) => Style::default().fg(Color::Yellow),
+		Some(Level::Debug) | Some(Level::Trace) => Style::default().fg(Color::DarkGray),
+		_ => Style::default(),
+	}
+}
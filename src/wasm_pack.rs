@@ -0,0 +1,122 @@
+use crate::{
+	diagnostics::Diagnostic,
+	error::{Error, Result},
+	tui,
+};
+use serde::Deserialize;
+use std::{io::ErrorKind, path::PathBuf, process::Stdio, time::Instant};
+use tokio::{
+	io::{AsyncBufReadExt, AsyncRead, BufReader},
+	process::{Child, Command},
+};
+
+#[derive(Clone)]
+pub struct BuildSettings {
+	pub app_dir: PathBuf,
+	pub target: String,
+	pub profile: Profile,
+	pub features: Vec<String>,
+	pub dist_dir: PathBuf,
+}
+
+#[derive(Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Profile {
+	Dev,
+	Release,
+	Profiling,
+}
+
+impl Default for BuildSettings {
+	fn default() -> Self {
+		Self {
+			app_dir: "app".into(),
+			target: "web".into(),
+			profile: Profile::Dev,
+			features: Vec::new(),
+			dist_dir: "dist".into(),
+		}
+	}
+}
+
+impl Profile {
+	fn flag(self) -> &'static str {
+		match self {
+			Profile::Dev => "--dev",
+			Profile::Release => "--release",
+			Profile::Profiling => "--profiling",
+		}
+	}
+}
+
+/// Runs wasm-pack and returns the warnings of a successful build.
+/// Dropping the returned future kills a build that is still running.
+pub async fn build(settings: &BuildSettings) -> Result<Vec<Diagnostic>> {
+	let manifest = settings.app_dir.join("Cargo.toml");
+	if !manifest.is_file() {
+		return Err(Error::Usage(format!(
+			"no app found at {}, create one with `cui-tools new`",
+			manifest.display()
+		)));
+	}
+
+	log::info!("building wasm target in {}...", settings.app_dir.display());
+	let started = Instant::now();
+	let mut command = Command::new("wasm-pack");
+	command
+		.args(["build", "--target", &settings.target, settings.profile.flag()])
+		.args(["--", "--message-format=json"])
+		.current_dir(&settings.app_dir)
+		.stdout(Stdio::piped())
+		.stderr(Stdio::piped())
+		.kill_on_drop(true);
+	if !settings.features.is_empty() {
+		command.args(["--features", &settings.features.join(",")]);
+	}
+
+	let mut child = spawn(&mut command)?;
+	let stdout = read(child.stdout.take().expect("stdout is piped"), Diagnostic::from_json);
+	let stderr = read(child.stderr.take().expect("stderr is piped"), Diagnostic::from_text);
+	let (stdout, stderr) = tokio::join!(stdout, stderr);
+	let mut diagnostics = stdout?;
+	diagnostics.extend(stderr?);
+
+	let status = child.wait().await?;
+	if !status.success() {
+		return Err(Error::Build {
+			message: format!("wasm-pack exited with {}", status),
+			diagnostics,
+		});
+	}
+	log::info!("built in {:.1}s", started.elapsed().as_secs_f32());
+	Ok(diagnostics)
+}
+
+/// Echoes a stream of build output to the terminal, with compiler messages printed
+/// the way cargo would have, and collects the diagnostics in it.
+async fn read(
+	stream: impl AsyncRead + Unpin,
+	parse: fn(&str) -> Option<Diagnostic>,
+) -> std::io::Result<Vec<Diagnostic>> {
+	let mut diagnostics = Vec::new();
+	let mut lines = BufReader::new(stream).lines();
+	while let Some(line) = lines.next_line().await? {
+		match parse(&line) {
+			Some(diagnostic) => {
+				tui::print_build_output(&diagnostic.rendered);
+				diagnostics.push(diagnostic);
+			}
+			// other JSON messages are artifact notifications nobody needs to see
+			None if line.starts_with('{') => {}
+			None => tui::print_build_output(&line),
+		}
+	}
+	Ok(diagnostics)
+}
+
+fn spawn(command: &mut Command) -> Result<Child> {
+	command.spawn().map_err(|error| match error.kind() {
+		ErrorKind::NotFound => Error::MissingTool(command.as_std().get_program().to_string_lossy().into_owned()),
+		_ => Error::Io(error),
+	})
+}
@@ -0,0 +1,175 @@
+use crate::{
+	diagnostics::Diagnostic,
+	error::{Error, Result},
+	events::{Event, Events},
+	wasm_pack::{self, BuildSettings},
+};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher as _};
+use std::{
+	path::{Path, PathBuf},
+	time::{Duration, Instant},
+};
+use tokio::{
+	sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+	time,
+};
+
+const EXTENSIONS: &[&str] = &["rs", "cui", "toml"];
+const IGNORED_DIRS: &[&str] = &["target", "pkg", ".git", "node_modules"];
+
+#[derive(Clone)]
+pub struct WatchSettings {
+	pub enabled: bool,
+	pub debounce: Duration,
+	pub paths: Vec<PathBuf>,
+}
+
+impl Default for WatchSettings {
+	fn default() -> Self {
+		Self {
+			enabled: true,
+			debounce: Duration::from_millis(200),
+			paths: Vec::new(),
+		}
+	}
+}
+
+/// Rebuilds the app when its sources change, or when asked to, until dropped.
+pub struct Watcher {
+	_watcher: Option<RecommendedWatcher>,
+	changes: UnboundedSender<Vec<PathBuf>>,
+}
+
+impl Watcher {
+	/// Starts a build as if a source had changed.
+	pub fn rebuild(&self) {
+		let _ = self.changes.send(Vec::new());
+	}
+}
+
+/// Starts the build loop, and watches the app sources when watching is enabled.
+pub fn spawn(settings: &WatchSettings, build: BuildSettings, events: Events) -> Result<Watcher> {
+	let (changes, receiver) = mpsc::unbounded_channel();
+	let watcher = match settings.enabled {
+		true => Some(watch(settings, changes.clone())?),
+		false => None,
+	};
+	actix_web::rt::spawn(rebuild(build, receiver, settings.debounce, events));
+	Ok(Watcher {
+		_watcher: watcher,
+		changes,
+	})
+}
+
+fn watch(settings: &WatchSettings, changes: UnboundedSender<Vec<PathBuf>>) -> Result<RecommendedWatcher> {
+	let roots = settings
+		.paths
+		.iter()
+		.map(|path| path.canonicalize())
+		.collect::<std::io::Result<Vec<_>>>()?;
+
+	let watched = roots.clone();
+	let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event {
+		Ok(event) if is_change(&event, &watched) => {
+			let _ = changes.send(event.paths);
+		}
+		Ok(_) => {}
+		Err(error) => log::warn!("file watcher error: {}", error),
+	})
+	.map_err(watch_error)?;
+
+	for path in &roots {
+		log::info!("watching {} for changes", path.display());
+		watcher.watch(path, RecursiveMode::Recursive).map_err(watch_error)?;
+	}
+	Ok(watcher)
+}
+
+/// Runs one build at a time, a change that arrives mid-build cancels it and starts over.
+async fn rebuild(
+	build: BuildSettings,
+	mut changes: UnboundedReceiver<Vec<PathBuf>>,
+	debounce: Duration,
+	events: Events,
+) {
+	while let Some(paths) = changes.recv().await {
+		log_changes(&paths);
+		settle(&mut changes, debounce).await;
+
+		loop {
+			tokio::select! {
+				result = self::build(&build, &events) => {
+					if let Err(error) = result {
+						log::error!("{}", error);
+					}
+					break;
+				}
+				Some(paths) = changes.recv() => {
+					log::info!("sources changed during the build, restarting it");
+					log_changes(&paths);
+					settle(&mut changes, debounce).await;
+				}
+			}
+		}
+	}
+}
+
+/// Builds the app once, reporting the outcome to the dev server.
+pub async fn build(build: &BuildSettings, events: &Events) -> Result<Vec<Diagnostic>> {
+	events.send(Event::Building);
+	let started = Instant::now();
+	let result = wasm_pack::build(build).await;
+	match &result {
+		Ok(diagnostics) => events.send(Event::Built {
+			duration_ms: started.elapsed().as_millis(),
+			diagnostics: diagnostics.clone(),
+		}),
+		Err(Error::Build { message, diagnostics }) => {
+			events.send(Event::BuildFailed {
+				message: message.clone(),
+				duration_ms: started.elapsed().as_millis(),
+				diagnostics: diagnostics.clone(),
+			});
+		}
+		Err(_) => {}
+	}
+	result
+}
+
+/// Waits until no changes have arrived for `debounce`, so a burst of saves causes one build.
+async fn settle(changes: &mut UnboundedReceiver<Vec<PathBuf>>, debounce: Duration) {
+	while let Ok(Some(paths)) = time::timeout(debounce, changes.recv()).await {
+		log_changes(&paths);
+	}
+}
+
+fn log_changes(paths: &[PathBuf]) {
+	for path in paths {
+		log::debug!("changed: {}", path.display());
+	}
+}
+
+fn is_change(event: &notify::Event, roots: &[PathBuf]) -> bool {
+	matches!(
+		event.kind,
+		EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+	) && event.paths.iter().any(|path| is_source(path, roots))
+}
+
+fn is_source(path: &Path, roots: &[PathBuf]) -> bool {
+	let relative = roots
+		.iter()
+		.find_map(|root| path.strip_prefix(root).ok())
+		.unwrap_or(path);
+	let ignored = relative.components().any(|component| {
+		IGNORED_DIRS
+			.iter()
+			.any(|dir| component.as_os_str() == *dir)
+	});
+	let extension = path.extension().and_then(|extension| extension.to_str());
+	!ignored && extension.is_some_and(|extension| EXTENSIONS.contains(&extension))
+}
+
+fn watch_error(error: notify::Error) -> Error {
+	Error::Other(format!("cannot watch for changes: {}", error))
+}